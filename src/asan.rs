@@ -1,61 +1,76 @@
-use crate::gunit::{TestCase, TestFailure, TestInfo};
-use colored::Colorize;
+use crate::paths::PathMapper;
+use crate::report::MemoryFinding;
 use std::path::Path;
 
-pub struct Asan;
+/// Sanitizers whose errors are reported
+const SANITIZERS: [&str; 3] = [
+    "AddressSanitizer",
+    "UndefinedBehaviorSanitizer",
+    "LeakSanitizer",
+];
+
+/// A sanitizer log, as written to the standard error of the tests
+#[derive(Debug, Clone, Default)]
+pub struct Asan {
+    pub findings: Vec<MemoryFinding>,
+
+    /// Whether the tests were killed, by a signal or by a sanitizer aborting
+    pub crashed: bool,
+
+    log: String,
+}
 
 impl Asan {
-    pub fn try_from_file(path: &Path) -> Result<TestCase, TestCase> {
-        let file = std::fs::read_to_string(path);
-        let mut suite = TestCase {
-            name: "sanitizer".to_string(),
-            ..Default::default()
-        };
-
-        let file = match file {
-            Ok(file) => file,
-            Err(_) => return Ok(suite),
-        };
-
-        for (key, name) in [
-            ("address_sanitizer", "AddressSanitizer"),
-            ("undefined_behavior_sanitizer", "UndefinedBehaviorSanitizer"),
-            ("leak_sanitizer", "LeakSanitizer"),
-        ] {
-            let search = format!("ERROR: {name}: ");
-            let message: Option<String> = file
-                .split_once(&search)
-                .and_then(|(_, m)| Some(m.to_string().split_once('\n')?.0.to_string()));
-
-            let info = TestInfo {
-                name: key.to_string(),
-                classname: "sanitizer".to_string(),
-                failures: if let Some(message) = message {
-                    vec![TestFailure {
-                        failure: message,
-                        ..Default::default()
-                    }]
-                } else {
-                    vec![]
-                },
-                ..Default::default()
-            };
-            suite.testsuite.push(info);
-        }
+    /// Read a sanitizer log, a missing log having no findings
+    pub fn from_file(path: &Path) -> Self {
+        std::fs::read_to_string(path)
+            .map(|log| Self::parse(&log))
+            .unwrap_or_default()
+    }
 
-        suite.tests = suite.testsuite.len() as u32;
-        suite.errors = (file.contains("DEADLYSIGNAL") || file.contains("ABORTING")).into();
-        suite.failures = suite
-            .testsuite
+    pub fn parse(log: &str) -> Self {
+        let findings = SANITIZERS
             .iter()
-            .filter(|info| !info.failures.is_empty())
-            .count() as u32;
-
-        if suite.failures == 0 {
-            Ok(suite)
-        } else {
-            print!("{}", file.bright_black());
-            Err(suite)
+            .filter_map(|sanitizer| {
+                let (_, message) = log.split_once(&format!("ERROR: {sanitizer}: "))?;
+                Some(MemoryFinding {
+                    sanitizer: sanitizer.to_string(),
+                    message: message.lines().next().unwrap_or_default().to_string(),
+                    test: None,
+                })
+            })
+            .collect();
+
+        Self {
+            findings,
+            crashed: log.contains("DEADLYSIGNAL") || log.contains("ABORTING"),
+            log: log.to_string(),
         }
     }
+
+    /// The signal a sanitizer caught before aborting, e.g. `SIGSEGV`
+    pub fn signal(&self) -> Option<String> {
+        SANITIZERS.iter().find_map(|sanitizer| {
+            let (_, message) = self.log.split_once(&format!("ERROR: {sanitizer}: "))?;
+            let (signal, _) = message.split_once(" on unknown address")?;
+            Some(format!("SIG{signal}"))
+        })
+    }
+
+    /// The part of the log reporting the crash, from the `DEADLYSIGNAL` line when there is one
+    pub fn crash_report(&self) -> Option<&str> {
+        if !self.crashed {
+            return None;
+        }
+        let start = ["DEADLYSIGNAL", "ERROR: "]
+            .iter()
+            .find_map(|marker| self.log.find(marker))?;
+        let start = self.log[..start].rfind('\n').map_or(0, |i| i + 1);
+        Some(self.log[start..].trim_end())
+    }
+
+    /// The log, with the paths of the workspace, when a sanitizer reported an error
+    pub fn report(&self, paths: &PathMapper) -> Option<String> {
+        (!self.findings.is_empty()).then(|| paths.rewrite(&self.log))
+    }
 }
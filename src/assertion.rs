@@ -0,0 +1,622 @@
+use colored::{ColoredString, Colorize};
+use itertools::Itertools;
+use similar::{ChangeTag, TextDiff};
+use std::ops::Range;
+
+const COMPARISON_OPERATORS: [&str; 6] = ["==", "!=", "<=", ">=", "<", ">"];
+
+/// A googletest assertion, parsed from the message of a failure
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Assertion {
+    /// `EXPECT_EQ`, `EXPECT_STREQ`, `EXPECT_STRCASEEQ`, ...
+    Equality { lhs: Operand, rhs: Operand },
+
+    /// `EXPECT_NE`, `EXPECT_LT`, `EXPECT_LE`, `EXPECT_GT`, `EXPECT_GE`, `EXPECT_STRNE`, ...
+    Comparison {
+        operator: String,
+        lhs: Operand,
+        rhs: Operand,
+    },
+
+    /// `EXPECT_NEAR`
+    Near {
+        lhs: Operand,
+        rhs: Operand,
+        tolerance: Operand,
+        difference: String,
+    },
+
+    /// `EXPECT_THAT`, but also `EXPECT_TRUE` and `EXPECT_FALSE`
+    Matcher {
+        value: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+/// One side of an assertion: the expression as written in the test and the value it evaluated to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Operand {
+    pub expression: String,
+    pub value: String,
+}
+
+impl Operand {
+    /// Literals are printed by googletest without a "Which is:" line
+    fn is_literal(&self) -> bool {
+        self.expression == self.value
+    }
+}
+
+impl Assertion {
+    pub fn parse(message: &str) -> Option<Self> {
+        Self::parse_equality(message)
+            .or_else(|| Self::parse_comparison(message))
+            .or_else(|| Self::parse_near(message))
+            .or_else(|| Self::parse_matcher(message))
+    }
+
+    /// ```text
+    /// Expected equality of these values:
+    ///   add(1, 2)
+    ///     Which is: 4
+    ///   3
+    /// ```
+    fn parse_equality(message: &str) -> Option<Self> {
+        let mut lines = message
+            .lines()
+            .skip_while(|line| !line.starts_with("Expected equality of these values:"))
+            .skip(1);
+
+        let mut operands: Vec<Operand> = Vec::new();
+        for line in lines.by_ref() {
+            if let Some(value) = line.strip_prefix("    Which is: ") {
+                operands.last_mut()?.value = value.to_string();
+            } else if let Some(expression) = line.strip_prefix("  ") {
+                operands.push(Operand {
+                    expression: expression.to_string(),
+                    value: expression.to_string(),
+                });
+            } else {
+                break;
+            }
+        }
+
+        let (lhs, rhs) = operands.into_iter().collect_tuple()?;
+        Some(Self::Equality { lhs, rhs })
+    }
+
+    /// ```text
+    /// Expected: (a) < (b), actual: 5 vs 3
+    /// ```
+    fn parse_comparison(message: &str) -> Option<Self> {
+        let line = message
+            .lines()
+            .find_map(|line| line.strip_prefix("Expected: ("))?;
+        let (expressions, values) = line.split_once(", actual: ")?;
+        let expressions = expressions
+            .strip_suffix(" (ignoring case)")
+            .unwrap_or(expressions)
+            .strip_suffix(')')?;
+
+        let (operator, (lhs, rhs)) = COMPARISON_OPERATORS.iter().find_map(|operator| {
+            Some((
+                operator.to_string(),
+                expressions.split_once(&format!(") {operator} ("))?,
+            ))
+        })?;
+        let (lhs_value, rhs_value) = values.split_once(" vs ")?;
+
+        Some(Self::Comparison {
+            operator,
+            lhs: Operand {
+                expression: lhs.to_string(),
+                value: lhs_value.to_string(),
+            },
+            rhs: Operand {
+                expression: rhs.to_string(),
+                value: rhs_value.to_string(),
+            },
+        })
+    }
+
+    /// ```text
+    /// The difference between a and b is 0.5, which exceeds tol, where
+    /// a evaluates to 1.5,
+    /// b evaluates to 1, and
+    /// tol evaluates to 0.1.
+    /// ```
+    fn parse_near(message: &str) -> Option<Self> {
+        let mut lines = message
+            .lines()
+            .skip_while(|line| !line.starts_with("The difference between "));
+
+        let header = lines.next()?;
+        let (header, _) = header.rsplit_once(", which exceeds ")?;
+        let (_, difference) = header.rsplit_once(" is ")?;
+
+        let (lhs, rhs, tolerance) = lines
+            .take(3)
+            .map(|line| {
+                let (expression, value) = line.rsplit_once(" evaluates to ")?;
+                let value = value
+                    .strip_suffix(", and")
+                    .or_else(|| value.strip_suffix(','))
+                    .or_else(|| value.strip_suffix('.'))
+                    .unwrap_or(value);
+
+                Some(Operand {
+                    expression: expression.to_string(),
+                    value: value.to_string(),
+                })
+            })
+            .collect::<Option<Vec<_>>>()?
+            .into_iter()
+            .collect_tuple()?;
+
+        Some(Self::Near {
+            lhs,
+            rhs,
+            tolerance,
+            difference: difference.to_string(),
+        })
+    }
+
+    /// ```text
+    /// Value of: v
+    /// Expected: has 3 elements
+    ///   Actual: { 1, 2 }, which has 2 elements
+    /// ```
+    fn parse_matcher(message: &str) -> Option<Self> {
+        let field = |prefix: &str| {
+            message
+                .lines()
+                .find_map(|line| line.trim_start().strip_prefix(prefix))
+                .map(str::to_string)
+        };
+
+        Some(Self::Matcher {
+            value: field("Value of: ")?,
+            expected: field("Expected: ")?,
+            actual: field("Actual: ")?,
+        })
+    }
+
+    /// Render the assertion as "expected / actual", followed by a diff of the values, in color
+    /// with `color`
+    pub fn render(&self, color: bool) -> String {
+        self.summary(color)
+            .into_iter()
+            .chain(self.diff(color))
+            .join("\n")
+    }
+
+    fn summary(&self, color: bool) -> Vec<String> {
+        match self {
+            Self::Equality { lhs, rhs } => {
+                let (expected, actual) = expected_and_actual(lhs, rhs);
+                vec![
+                    format!(
+                        "expected: {}{}",
+                        expected.value,
+                        expression(expected, color)
+                    ),
+                    format!("  actual: {}{}", actual.value, expression(actual, color)),
+                ]
+            }
+            Self::Comparison { operator, lhs, rhs } => vec![
+                format!("expected: {} {operator} {}", lhs.expression, rhs.expression),
+                format!("  actual: {} vs {}", lhs.value, rhs.value),
+            ],
+            Self::Near {
+                lhs,
+                rhs,
+                tolerance,
+                ..
+            } => vec![
+                format!(
+                    "expected: {} ± {}{}",
+                    rhs.value,
+                    tolerance.value,
+                    expression(rhs, color)
+                ),
+                format!("  actual: {}{}", lhs.value, expression(lhs, color)),
+            ],
+            Self::Matcher {
+                value,
+                expected,
+                actual,
+            } => vec![
+                format!(
+                    "expected: {expected} {}",
+                    paint(color, &format!("({value})"), |text| text.bright_black())
+                ),
+                format!("  actual: {actual}"),
+            ],
+        }
+    }
+
+    /// The difference between the values that made the assertion fail
+    pub fn diff(&self, color: bool) -> Vec<String> {
+        match self {
+            Self::Equality { lhs, rhs } => {
+                let (expected, actual) = expected_and_actual(lhs, rhs);
+                diff(&expected.value, &actual.value, color)
+            }
+            Self::Comparison { lhs, rhs, .. } => {
+                delta(&rhs.value, &lhs.value, color).into_iter().collect()
+            }
+            Self::Near {
+                tolerance,
+                difference,
+                ..
+            } => vec![format!(
+                "   delta: {} {}",
+                paint(color, difference, |text| text.yellow()),
+                paint(color, &format!("(exceeds {})", tolerance.value), |text| {
+                    text.bright_black()
+                })
+            )],
+            Self::Matcher {
+                expected, actual, ..
+            } => match expected.strip_prefix("is equal to ") {
+                Some(expected) => diff(expected, actual, color),
+                None => vec![],
+            },
+        }
+    }
+
+    /// Labels for the operands of the assertion, located in `source` (the assertion as written
+    /// in the test file). Operands that cannot be found in the source have no range.
+    pub fn labels(&self, source: &str) -> Vec<(Option<Range<usize>>, String)> {
+        match self {
+            Self::Equality { lhs, rhs } => {
+                let (expected, _) = expected_and_actual(lhs, rhs);
+                let name = |operand: &Operand| {
+                    if std::ptr::eq(operand, expected) {
+                        "expected"
+                    } else {
+                        "actual"
+                    }
+                };
+
+                vec![
+                    (
+                        locate(source, &lhs.expression, false),
+                        format!("{}: {}", name(lhs), lhs.value),
+                    ),
+                    (
+                        locate(source, &rhs.expression, true),
+                        format!("{}: {}", name(rhs), rhs.value),
+                    ),
+                ]
+            }
+            Self::Comparison { lhs, rhs, .. } => vec![
+                (
+                    locate(source, &lhs.expression, false),
+                    format!("which is {}", lhs.value),
+                ),
+                (
+                    locate(source, &rhs.expression, true),
+                    format!("which is {}", rhs.value),
+                ),
+            ],
+            Self::Near {
+                lhs,
+                rhs,
+                tolerance,
+                ..
+            } => vec![
+                (
+                    locate(source, &lhs.expression, false),
+                    format!("actual: {}", lhs.value),
+                ),
+                (
+                    locate(source, &rhs.expression, false),
+                    format!("expected: {}", rhs.value),
+                ),
+                (
+                    locate(source, &tolerance.expression, true),
+                    format!("tolerance: {}", tolerance.value),
+                ),
+            ],
+            Self::Matcher {
+                value,
+                expected,
+                actual,
+            } => vec![
+                (locate(source, value, false), format!("actual: {actual}")),
+                (None, format!("expected: {expected}")),
+            ],
+        }
+    }
+}
+
+/// Literals are more likely to be the expected value, otherwise fall back to googletest's
+/// historical `EXPECT_EQ(expected, actual)` order
+fn expected_and_actual<'a>(lhs: &'a Operand, rhs: &'a Operand) -> (&'a Operand, &'a Operand) {
+    if rhs.is_literal() && !lhs.is_literal() {
+        (rhs, lhs)
+    } else {
+        (lhs, rhs)
+    }
+}
+
+/// Find an operand within the arguments of the assertion macro
+fn locate(source: &str, expression: &str, from_end: bool) -> Option<Range<usize>> {
+    if expression.is_empty() {
+        return None;
+    }
+
+    let offset = source.find('(')? + 1;
+    let arguments = &source[offset..];
+    let start = offset
+        + if from_end {
+            arguments.rfind(expression)?
+        } else {
+            arguments.find(expression)?
+        };
+
+    Some(start..start + expression.len())
+}
+
+fn expression(operand: &Operand, color: bool) -> String {
+    if operand.is_literal() {
+        String::new()
+    } else {
+        let expression = format!("({})", operand.expression);
+        format!(" {}", paint(color, &expression, |text| text.bright_black()))
+    }
+}
+
+/// Diff two values the most useful way for their type: numbers by their delta,
+/// containers element by element, multi-line strings by line and anything else by character
+fn diff(expected: &str, actual: &str, color: bool) -> Vec<String> {
+    if let Some(delta) = delta(expected, actual, color) {
+        return vec![delta];
+    }
+
+    if let (Some(expected), Some(actual)) = (elements(expected), elements(actual)) {
+        return diff_lines(&expected, &actual, color);
+    }
+
+    if expected.contains("\\n") || actual.contains("\\n") {
+        let expected = expected.split("\\n").collect_vec();
+        let actual = actual.split("\\n").collect_vec();
+        return diff_lines(&expected, &actual, color);
+    }
+
+    let diff = TextDiff::from_chars(expected, actual);
+    let (mut old, mut new) = (String::new(), String::new());
+    for change in diff.iter_all_changes() {
+        let value = change.value();
+        match change.tag() {
+            ChangeTag::Equal => {
+                old.push_str(value);
+                new.push_str(value);
+            }
+            ChangeTag::Delete => {
+                old.push_str(&paint(color, value, |text| text.green().underline()))
+            }
+            ChangeTag::Insert => new.push_str(&paint(color, value, |text| text.red().underline())),
+        }
+    }
+
+    vec![
+        format!("    diff: {} {old}", paint(color, "-", |text| text.green())),
+        format!("          {} {new}", paint(color, "+", |text| text.red())),
+    ]
+}
+
+fn diff_lines(expected: &[&str], actual: &[&str], color: bool) -> Vec<String> {
+    let diff = TextDiff::from_slices(expected, actual);
+    let mut lines = vec!["    diff:".to_string()];
+    for change in diff.iter_all_changes() {
+        let value = change.value();
+        lines.push(match change.tag() {
+            ChangeTag::Equal => format!("        {value}"),
+            ChangeTag::Delete => {
+                format!(
+                    "      {}",
+                    paint(color, &format!("- {value}"), |text| text.green())
+                )
+            }
+            ChangeTag::Insert => {
+                format!(
+                    "      {}",
+                    paint(color, &format!("+ {value}"), |text| text.red())
+                )
+            }
+        });
+    }
+    lines
+}
+
+fn delta(expected: &str, actual: &str, color: bool) -> Option<String> {
+    let delta =
+        if let (Ok(expected), Ok(actual)) = (expected.parse::<i128>(), actual.parse::<i128>()) {
+            format!("{:+}", actual - expected)
+        } else {
+            let expected = expected.parse::<f64>().ok()?;
+            let actual = actual.parse::<f64>().ok()?;
+            format!("{:+}", actual - expected)
+        };
+
+    Some(format!(
+        "   delta: {}",
+        paint(color, &delta, |text| text.yellow())
+    ))
+}
+
+/// `text` in the style of `style`, or as is without `color`
+fn paint(color: bool, text: &str, style: fn(&str) -> ColoredString) -> String {
+    if color {
+        style(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Split a container printed by googletest (`{ 1, 2, 3 }`) into its elements
+fn elements(value: &str) -> Option<Vec<&str>> {
+    let inner = value.strip_prefix('{')?.strip_suffix('}')?.trim();
+    if inner.is_empty() {
+        return Some(vec![]);
+    }
+
+    let mut elements = Vec::new();
+    let (mut depth, mut quoted, mut escaped, mut start) = (0, false, false, 0);
+    for (i, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '{' | '(' | '[' if !quoted => depth += 1,
+            '}' | ')' | ']' if !quoted => depth -= 1,
+            ',' if !quoted && depth == 0 => {
+                elements.push(inner[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(inner[start..].trim());
+
+    Some(elements)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The messages of googletest 1.14, without the `file:line` line they start with
+    const EXPECT_EQ: &str =
+        "Expected equality of these values:\n  add(1, 2)\n    Which is: 3\n  4\n";
+    const EXPECT_STRCASEEQ: &str =
+        "Expected equality of these values:\n  \"hello\"\n  greet()\n    \
+         Which is: \"Hallo\"\nIgnoring case\n";
+    const EXPECT_LT: &str = "Expected: (size(v)) < (3), actual: 5 vs 3\n";
+    const EXPECT_NEAR: &str = "The difference between average(v) and 2.5 is 0.5, which exceeds \
+                               0.1, where\naverage(v) evaluates to 3,\n2.5 evaluates to 2.5, \
+                               and\n0.1 evaluates to 0.10000000000000001.\n";
+    const EXPECT_TRUE: &str = "Value of: is_even(3)\n  Actual: true\nExpected: false\n";
+    const EXPECT_THAT: &str =
+        "Value of: sorted(v)\nExpected: is equal to { 1, 2, 3 }\n  Actual: { 1, 3, 2 }\n";
+
+    fn operand(expression: &str, value: &str) -> Operand {
+        Operand {
+            expression: expression.to_string(),
+            value: value.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_equalities() {
+        assert_eq!(
+            Assertion::parse(EXPECT_EQ),
+            Some(Assertion::Equality {
+                lhs: operand("add(1, 2)", "3"),
+                rhs: operand("4", "4"),
+            })
+        );
+        assert_eq!(
+            Assertion::parse(EXPECT_STRCASEEQ),
+            Some(Assertion::Equality {
+                lhs: operand("\"hello\"", "\"hello\""),
+                rhs: operand("greet()", "\"Hallo\""),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_comparisons() {
+        assert_eq!(
+            Assertion::parse(EXPECT_LT),
+            Some(Assertion::Comparison {
+                operator: "<".to_string(),
+                lhs: operand("size(v)", "5"),
+                rhs: operand("3", "3"),
+            })
+        );
+        assert_eq!(
+            Assertion::parse(EXPECT_NEAR),
+            Some(Assertion::Near {
+                lhs: operand("average(v)", "3"),
+                rhs: operand("2.5", "2.5"),
+                tolerance: operand("0.1", "0.10000000000000001"),
+                difference: "0.5".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn parses_matchers() {
+        assert_eq!(
+            Assertion::parse(EXPECT_TRUE),
+            Some(Assertion::Matcher {
+                value: "is_even(3)".to_string(),
+                expected: "false".to_string(),
+                actual: "true".to_string(),
+            })
+        );
+        assert_eq!(Assertion::parse("Failed\nunreachable code\n"), None);
+    }
+
+    #[test]
+    fn literals_are_expected() {
+        let lines = Assertion::parse(EXPECT_EQ).unwrap().summary(false);
+        assert_eq!(lines, ["expected: 4", "  actual: 3 (add(1, 2))"]);
+    }
+
+    #[test]
+    fn diffs_by_type() {
+        let diff = |message| Assertion::parse(message).unwrap().diff(false);
+        assert_eq!(diff(EXPECT_EQ), ["   delta: -1"]);
+        assert_eq!(diff(EXPECT_LT), ["   delta: +2"]);
+        assert_eq!(
+            diff(EXPECT_THAT),
+            [
+                "    diff:",
+                "        1",
+                "      + 3",
+                "        2",
+                "      - 3",
+            ]
+        );
+        assert_eq!(
+            super::diff("\"a\\nb\"", "\"a\\nc\"", false),
+            ["    diff:", "        \"a", "      - b\"", "      + c\""]
+        );
+        assert_eq!(
+            super::diff("\"hello\"", "\"hallo\"", false),
+            ["    diff: - \"hello\"", "          + \"hallo\""]
+        );
+    }
+
+    #[test]
+    fn splits_containers() {
+        assert_eq!(
+            elements(r#"{ "a, b", { 1, 2 }, (3, 4), "\"" }"#),
+            Some(vec![r#""a, b""#, "{ 1, 2 }", "(3, 4)", r#""\"""#])
+        );
+        assert_eq!(elements("{}"), Some(vec![]));
+        assert_eq!(elements("42"), None);
+    }
+
+    #[test]
+    fn labels_the_operands() {
+        let source = "EXPECT_EQ(add(1, 2), 4);";
+        let labels = Assertion::parse(EXPECT_EQ).unwrap().labels(source);
+        let located = labels
+            .iter()
+            .map(|(range, label)| (&source[range.clone().unwrap()], label.as_str()))
+            .collect_vec();
+        assert_eq!(located, [("add(1, 2)", "actual: 3"), ("4", "expected: 4")]);
+
+        // An operand built by a macro is not in the source
+        let labels = Assertion::parse(EXPECT_LT)
+            .unwrap()
+            .labels("CHECK_SIZE(v, 3);");
+        assert_eq!(labels[0].0, None);
+    }
+}
@@ -0,0 +1,309 @@
+use super::Exit;
+use crate::credentials::{Credential, Credentials, CredentialsError, TokenKind};
+use crate::gitlab::{GitLab, GitLabError};
+use colored::Colorize;
+use std::io::{BufRead, IsTerminal};
+use std::process::{Command, Stdio};
+
+/// Scopes needed to fork, clone and push the exercises
+const OAUTH_SCOPE: &str = "api read_user write_repository";
+
+/// Environment variable with the personal access token to log in with, kept off the command line
+const TOKEN_ENV: &str = "NEODOJO_GITLAB_TOKEN";
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("{0}")]
+    Credentials(#[from] CredentialsError),
+
+    #[error("{0}")]
+    GitLab(#[from] GitLabError),
+
+    #[error("unable to read the token: {0}")]
+    Input(#[from] std::io::Error),
+
+    #[error("no token given")]
+    EmptyToken,
+
+    #[error("not logged in to {0}, run `neodojo login`")]
+    NotLoggedIn(String),
+}
+
+/// Log in with the token read from stdin with `token_stdin`, from `$NEODOJO_GITLAB_TOKEN`, through
+/// the device flow of the OAuth application `client_id` or else prompted
+pub fn login(host: &str, token_stdin: bool, client_id: Option<&str>) -> Exit {
+    let token = match (token_stdin, std::env::var(TOKEN_ENV)) {
+        (true, _) => read_line().map(Some),
+        (false, Ok(token)) if !token.trim().is_empty() => Ok(Some(token.trim().to_string())),
+        (false, _) => Ok(None),
+    };
+
+    match token.and_then(|token| authenticate(host, token, client_id)) {
+        Ok(credential) => {
+            println!(
+                "{} logged in to {host} as {}",
+                "✔".green().bold(),
+                credential.username.bold()
+            );
+            Exit::Success
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+pub fn logout(host: &str) -> Exit {
+    match revoke(host) {
+        Ok(Some(warning)) => {
+            println!("{}{} {warning}", "warning".yellow().bold(), ":".bold());
+            println!("{} logged out from {host}", "✔".green().bold());
+            Exit::Success
+        }
+        Ok(None) => {
+            println!("{} logged out from {host}", "✔".green().bold());
+            Exit::Success
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+pub fn whoami(host: &str) -> Exit {
+    match client(host).and_then(|gitlab| Ok(gitlab.user()?)) {
+        Ok(user) => {
+            println!("{} ({}) on {host}", user.username.bold(), user.name);
+            Exit::Success
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+/// A client authenticated with the stored credentials of `host`
+pub fn client(host: &str) -> Result<GitLab, AuthError> {
+    let credentials = Credentials::load()?;
+    let credential = credentials
+        .get(host)
+        .ok_or_else(|| AuthError::NotLoggedIn(host.to_string()))?;
+    Ok(GitLab::new(host).with_token(&credential.token))
+}
+
+fn authenticate(
+    host: &str,
+    token: Option<String>,
+    client_id: Option<&str>,
+) -> Result<Credential, AuthError> {
+    let gitlab = GitLab::new(host);
+    let (token, kind) = match (token, client_id) {
+        (_, Some(client_id)) => (device_flow(&gitlab, client_id)?, TokenKind::OAuth),
+        (Some(token), None) => (token, TokenKind::PersonalAccessToken),
+        (None, None) => (read_token(host)?, TokenKind::PersonalAccessToken),
+    };
+
+    // Only keep tokens that actually work
+    let user = gitlab.clone().with_token(&token).user()?;
+    let credential = Credential {
+        token,
+        kind,
+        client_id: client_id
+            .filter(|_| kind == TokenKind::OAuth)
+            .map(str::to_string),
+        username: user.username,
+    };
+
+    let mut credentials = Credentials::load()?;
+    credentials.insert(host, credential.clone());
+    credentials.save()?;
+    Ok(credential)
+}
+
+fn device_flow(gitlab: &GitLab, client_id: &str) -> Result<String, AuthError> {
+    let authorization = gitlab.authorize_device(client_id, OAUTH_SCOPE)?;
+    println!(
+        "Open {} and enter the code {}",
+        authorization.verification_uri.underline(),
+        authorization.user_code.bold()
+    );
+    if let Some(uri) = &authorization.verification_uri_complete {
+        println!("{}", format!("or open {uri} directly").bright_black());
+    }
+    println!("{}", "Waiting for the authorization...".bright_black());
+
+    Ok(gitlab
+        .poll_device_token(client_id, &authorization)?
+        .access_token)
+}
+
+fn read_token(host: &str) -> Result<String, AuthError> {
+    println!(
+        "Create a personal access token with the {} scopes at {}",
+        "api, read_user, write_repository".bold(),
+        format!("{host}/-/user_settings/personal_access_tokens").underline()
+    );
+    println!("Paste it here:");
+    if !std::io::stdin().is_terminal() {
+        return read_line();
+    }
+
+    // Keep the token off the screen, and off the scrollback of the terminal
+    let hidden = stty("-echo");
+    let token = read_line();
+    if hidden {
+        stty("echo");
+        println!();
+    }
+    token
+}
+
+/// Change a setting of the terminal of stdin, returning whether it was
+fn stty(setting: &str) -> bool {
+    Command::new("stty")
+        .arg(setting)
+        .stdin(Stdio::inherit())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// A token given on a line of stdin
+fn read_line() -> Result<String, AuthError> {
+    let mut token = String::new();
+    std::io::stdin().lock().read_line(&mut token)?;
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(AuthError::EmptyToken);
+    }
+    Ok(token.to_string())
+}
+
+/// Revoke the token of `host` and forget it, returning a warning if only the latter worked
+fn revoke(host: &str) -> Result<Option<String>, AuthError> {
+    let mut credentials = Credentials::load()?;
+    let credential = credentials
+        .remove(host)
+        .ok_or_else(|| AuthError::NotLoggedIn(host.to_string()))?;
+
+    let gitlab = GitLab::new(host).with_token(&credential.token);
+    let revoked = match (credential.kind, &credential.client_id) {
+        (TokenKind::OAuth, Some(client_id)) => {
+            gitlab.revoke_oauth_token(client_id, &credential.token)
+        }
+        _ => gitlab.revoke_personal_access_token(),
+    };
+
+    // The token must be forgotten even when GitLab cannot be reached
+    credentials.save()?;
+    Ok(revoked
+        .err()
+        .map(|err| format!("the token could not be revoked, revoke it from GitLab: {err}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::tests::ENVIRONMENT;
+    use crate::config::CONFIG_DIR_ENV;
+    use crate::gitlab::tests::MockServer;
+
+    const USER: &str = r#"{"id":1,"username":"ada","name":"Ada Lovelace","state":"active"}"#;
+
+    #[test]
+    fn login_with_a_personal_access_token() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CONFIG_DIR_ENV, dir.path());
+        let server = MockServer::start(&[
+            ("GET /api/v4/user", 200, USER),
+            ("DELETE /api/v4/personal_access_tokens/self", 204, ""),
+        ]);
+
+        let credential = authenticate(&server.host, Some("secret".to_string()), None).unwrap();
+        assert_eq!(credential.username, "ada");
+        assert_eq!(credential.kind, TokenKind::PersonalAccessToken);
+
+        // whoami
+        assert_eq!(
+            client(&server.host).unwrap().user().unwrap().name,
+            "Ada Lovelace"
+        );
+
+        assert_eq!(revoke(&server.host).unwrap(), None);
+        assert!(matches!(
+            client(&server.host),
+            Err(AuthError::NotLoggedIn(_))
+        ));
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert!(requests.iter().all(|request| request[2] == "Bearer secret"));
+    }
+
+    #[test]
+    fn login_with_the_device_flow() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CONFIG_DIR_ENV, dir.path());
+        let server = MockServer::start(&[
+            (
+                "POST /oauth/authorize_device",
+                200,
+                r#"{"device_code":"device","user_code":"WDJB-MJHT","verification_uri":"https://gitlab.example.com/oauth/device","expires_in":300,"interval":0}"#,
+            ),
+            (
+                "POST /oauth/token",
+                200,
+                r#"{"access_token":"oauth-secret"}"#,
+            ),
+            ("GET /api/v4/user", 200, USER),
+            ("POST /oauth/revoke", 200, "{}"),
+        ]);
+
+        let credential = authenticate(&server.host, None, Some("app")).unwrap();
+        assert_eq!(credential.kind, TokenKind::OAuth);
+        assert_eq!(credential.client_id.as_deref(), Some("app"));
+
+        assert_eq!(revoke(&server.host).unwrap(), None);
+        let requests = server.requests();
+        assert_eq!(requests[3][1], "/oauth/revoke");
+        assert_eq!(requests[3][3], "client_id=app&token=oauth-secret");
+    }
+
+    #[test]
+    fn failed_logins_are_not_kept() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CONFIG_DIR_ENV, dir.path());
+        let server =
+            MockServer::start(&[("GET /api/v4/user", 401, r#"{"message":"401 Unauthorized"}"#)]);
+
+        let error = authenticate(&server.host, Some("wrong".to_string()), None).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "GitLab responded with 401: 401 Unauthorized"
+        );
+        assert!(matches!(
+            client(&server.host),
+            Err(AuthError::NotLoggedIn(_))
+        ));
+    }
+
+    #[test]
+    fn logout_forgets_tokens_that_cannot_be_revoked() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CONFIG_DIR_ENV, dir.path());
+        let server = MockServer::start(&[("GET /api/v4/user", 200, USER)]);
+
+        authenticate(&server.host, Some("secret".to_string()), None).unwrap();
+        let warning = revoke(&server.host).unwrap().unwrap();
+        assert!(warning.starts_with("the token could not be revoked"));
+        assert!(matches!(
+            client(&server.host),
+            Err(AuthError::NotLoggedIn(_))
+        ));
+    }
+}
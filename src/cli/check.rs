@@ -0,0 +1,58 @@
+use super::Exit;
+use colored::Colorize;
+use neodojo::compose::{ComposeError, Environment, COMPOSE_FILE};
+use neodojo::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+enum CheckError {
+    #[error("invalid dojo assignment: {0}")]
+    Assignment(#[from] DojoAssignmentError),
+
+    #[error("invalid exercise environment: {0}")]
+    Compose(#[from] ComposeError),
+}
+
+pub fn command(root: &Path) -> Exit {
+    match check(root) {
+        Ok(()) => {
+            println!("{} the exercise is valid", "✔".green().bold());
+            Exit::Success
+        }
+        Err(err) => {
+            if let CheckError::Assignment(assignment) = &err {
+                assignment.pretty_print();
+            }
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+fn check(root: &Path) -> Result<(), CheckError> {
+    let assignment = DojoAssignment::try_from_file(&root.join(ASSIGNMENT_FILE))?;
+    println!("{} {ASSIGNMENT_FILE}", "✔".green().bold());
+
+    Environment::load(&root.join(COMPOSE_FILE), &assignment.result)?;
+    println!("{} {COMPOSE_FILE}", "✔".green().bold());
+
+    for file in &assignment.immutable {
+        let path = root.join(file.path.trim_start_matches('/'));
+        let exists = match file.is_directory {
+            Some(true) => path.is_dir(),
+            Some(false) => path.is_file(),
+            None => path.exists(),
+        };
+
+        if !exists {
+            println!(
+                "{}{} protected file '{}' does not exist",
+                "warning".yellow().bold(),
+                ":".bold(),
+                file.path
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,245 @@
+use super::auth::{self, AuthError};
+use super::test::wrap_progress;
+use super::Exit;
+use crate::git::{Git, GitError, UPSTREAM};
+use crate::gitlab::{GitLab, GitLabError, Project};
+use colored::Colorize;
+use neodojo::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// How long the repository of a new fork may take to be copied
+const FORK_TIMEOUT: Duration = Duration::from_secs(300);
+
+#[derive(Debug, thiserror::Error)]
+enum CloneError {
+    #[error("{0}")]
+    Auth(#[from] AuthError),
+
+    #[error("{0}")]
+    GitLab(#[from] GitLabError),
+
+    #[error("{0}")]
+    Git(#[from] GitError),
+
+    #[error("no assignment group given, use --group or `neodojo config set gitlab.group GROUP`")]
+    MissingGroup,
+
+    #[error("assignment '{0}' not found, run `neodojo fetch` to list the available ones")]
+    NotFound(String),
+
+    #[error("destination '{0}' already exists and is not empty")]
+    Exists(PathBuf),
+
+    #[error("the cloned exercise has an invalid dojo assignment: {0}")]
+    Assignment(#[from] DojoAssignmentError),
+}
+
+/// List the assignments published in `group`
+pub fn fetch(host: &str, group: Option<&str>) -> Exit {
+    let assignments = group.ok_or(CloneError::MissingGroup).and_then(|group| {
+        let gitlab = auth::client(host)?;
+        Ok((group, gitlab.group_projects(group)?))
+    });
+
+    match assignments {
+        Ok((group, assignments)) if assignments.is_empty() => {
+            println!("No assignment available in {}", group.cyan());
+        }
+        Ok((_, assignments)) => {
+            for assignment in assignments {
+                println!("{}", assignment.path_with_namespace.bold());
+                if let Some(description) = assignment.description.filter(|d| !d.is_empty()) {
+                    println!("    {}", description.bright_black());
+                }
+            }
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            return Exit::Error;
+        }
+    }
+    Exit::Success
+}
+
+/// Fork an assignment and clone the fork
+pub fn command(
+    host: &str,
+    group: Option<&str>,
+    assignment: &str,
+    directory: Option<&Path>,
+) -> Exit {
+    match clone(host, group, assignment, directory) {
+        Ok(destination) => {
+            println!(
+                "{} cloned exercise into {}, run `neodojo test -p {}` to test it",
+                "✔".green().bold(),
+                destination.display().to_string().cyan(),
+                destination.display()
+            );
+            Exit::Success
+        }
+        Err(err) => {
+            if let CloneError::Assignment(assignment) = &err {
+                assignment.pretty_print();
+            }
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+fn clone(
+    host: &str,
+    group: Option<&str>,
+    assignment: &str,
+    directory: Option<&Path>,
+) -> Result<PathBuf, CloneError> {
+    let gitlab = auth::client(host)?;
+    let project = find_assignment(&gitlab, group, assignment)?;
+
+    let destination = match directory {
+        Some(directory) => directory.to_path_buf(),
+        None => PathBuf::from(
+            project
+                .path_with_namespace
+                .rsplit('/')
+                .next()
+                .unwrap_or_default(),
+        ),
+    };
+    let not_empty = std::fs::read_dir(&destination).is_ok_and(|mut dir| dir.next().is_some());
+    if not_empty {
+        return Err(CloneError::Exists(destination));
+    }
+
+    let fork = wrap_progress("Forking assignment", || fork(&gitlab, &project))?;
+    println!(
+        "    {}",
+        format!("private fork {}", fork.path_with_namespace).bright_black()
+    );
+
+    let git = wrap_progress("Cloning exercise", || {
+        Git::clone(
+            &fork.http_url_to_repo,
+            &destination,
+            gitlab.git_auth_header().as_deref(),
+        )
+    })?;
+    // The updates of the assignment are pulled from it, see `Git::assignment_commit`
+    git.add_remote(UPSTREAM, &project.http_url_to_repo)?;
+    // Until they are fetched, the assignment is the commit it was cloned at
+    git.record_fork_point()?;
+
+    DojoAssignment::try_from_file(&destination.join(ASSIGNMENT_FILE))?;
+    Ok(destination)
+}
+
+/// An assignment, by its full path or by its name within `group`
+fn find_assignment(
+    gitlab: &GitLab,
+    group: Option<&str>,
+    assignment: &str,
+) -> Result<Project, CloneError> {
+    if assignment.contains('/') {
+        return gitlab.project(assignment).map_err(|err| match err {
+            GitLabError::Status(404, _) => CloneError::NotFound(assignment.to_string()),
+            err => err.into(),
+        });
+    }
+
+    let group = group.ok_or(CloneError::MissingGroup)?;
+    gitlab
+        .group_projects(group)?
+        .into_iter()
+        .find(|project| {
+            project.path_with_namespace.rsplit('/').next() == Some(assignment)
+                || project.name.eq_ignore_ascii_case(assignment)
+        })
+        .ok_or_else(|| CloneError::NotFound(assignment.to_string()))
+}
+
+/// The fork of the user, created if it does not exist yet
+fn fork(gitlab: &GitLab, assignment: &Project) -> Result<Project, GitLabError> {
+    let fork = match gitlab.owned_forks(assignment.id)?.into_iter().next() {
+        Some(fork) => fork,
+        None => gitlab.fork(assignment.id)?,
+    };
+    gitlab.wait_for_fork(fork, FORK_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::restore::{immutable_changes, restore};
+    use crate::config::tests::ENVIRONMENT;
+    use crate::config::CONFIG_DIR_ENV;
+    use crate::credentials::{Credential, Credentials, TokenKind};
+    use crate::git::tests::{commit, git};
+    use crate::gitlab::tests::MockServer;
+
+    const ASSIGNMENT: &str = r#"{
+        "dojoAssignmentVersion": 1,
+        "version": 1,
+        "immutable": [{ "description": "Tests", "path": "tests/", "isDirectory": true }],
+        "result": { "container": "exercise", "volume": "results" }
+    }"#;
+
+    fn project(id: u64, path: &str, repository: &Path) -> String {
+        format!(
+            r#"{{"id":{id},"name":"stack","path_with_namespace":"{path}","description":null,
+                "http_url_to_repo":"{}","import_status":"finished","import_error":null}}"#,
+            repository.display()
+        )
+    }
+
+    #[test]
+    fn updated_assignments_are_cloned_unchanged() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var(CONFIG_DIR_ENV, dir.path().join("config"));
+
+        // An assignment whose tests were fixed after it was published, and its fork
+        let upstream = dir.path().join("upstream");
+        std::fs::create_dir_all(upstream.join("tests")).unwrap();
+        git(&upstream, &["init", "--quiet", "--initial-branch=main"]);
+        commit(&upstream, ASSIGNMENT_FILE, ASSIGNMENT);
+        commit(&upstream, "tests/tests.c", "original tests");
+        commit(&upstream, "tests/tests.c", "fixed tests");
+        let fork = dir.path().join("fork.git");
+        git(
+            dir.path(),
+            &["clone", "--quiet", "--bare", "upstream", "fork.git"],
+        );
+
+        let assignment = project(7, "dojo/stack", &upstream);
+        let forks = format!("[{}]", project(42, "ada/stack", &fork));
+        let server = MockServer::start(&[
+            ("GET /api/v4/projects/dojo%2Fstack", 200, &assignment),
+            ("GET /api/v4/projects/7/forks", 200, &forks),
+        ]);
+        let mut credentials = Credentials::default();
+        credentials.insert(
+            &server.host,
+            Credential {
+                token: "secret".to_string(),
+                kind: TokenKind::PersonalAccessToken,
+                client_id: None,
+                username: "ada".to_string(),
+            },
+        );
+        credentials.save().unwrap();
+
+        let exercise = dir.path().join("exercise");
+        let destination = clone(&server.host, None, "dojo/stack", Some(&exercise)).unwrap();
+        assert_eq!(destination, exercise);
+
+        let assignment = DojoAssignment::try_from_file(&exercise.join(ASSIGNMENT_FILE)).unwrap();
+        assert_eq!(immutable_changes(&exercise, &assignment).unwrap().1, []);
+        assert_eq!(restore(&exercise).unwrap(), []);
+        assert_eq!(
+            std::fs::read_to_string(exercise.join("tests/tests.c")).unwrap(),
+            "fixed tests"
+        );
+    }
+}
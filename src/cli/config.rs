@@ -0,0 +1,76 @@
+use super::{ConfigAction, Exit};
+use crate::config::{self, ConfigError, Layers, Source, KEYS};
+use colored::Colorize;
+use std::path::Path;
+use toml::Value;
+
+pub fn command(action: &ConfigAction, workspace: Option<&Path>) -> Exit {
+    let result = match action {
+        ConfigAction::Get { key } => get(key, workspace),
+        ConfigAction::Set {
+            key,
+            value,
+            workspace: local,
+        } => set(key, value, workspace, *local),
+        ConfigAction::List => list(workspace),
+    };
+
+    match result {
+        Ok(()) => Exit::Success,
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+fn get(key: &str, workspace: Option<&Path>) -> Result<(), ConfigError> {
+    match Layers::load(workspace)?.get(key)? {
+        Some((value, _)) => println!("{}", display(value)),
+        None => println!("{}", "unset".bright_black()),
+    }
+    Ok(())
+}
+
+fn set(key: &str, value: &str, workspace: Option<&Path>, local: bool) -> Result<(), ConfigError> {
+    let source = if local {
+        Source::Workspace
+    } else {
+        Source::User
+    };
+    let path = Layers::path(source, workspace).ok_or(match source {
+        Source::Workspace => ConfigError::NoWorkspace,
+        _ => ConfigError::NoConfigDir,
+    })?;
+
+    config::set(&path, key, value)?;
+    println!(
+        "{} {key} set in {}",
+        "✔".green().bold(),
+        path.display().to_string().cyan()
+    );
+    Ok(())
+}
+
+fn list(workspace: Option<&Path>) -> Result<(), ConfigError> {
+    let layers = Layers::load(workspace)?;
+    for key in KEYS {
+        match layers.get(key)? {
+            Some((value, source)) => println!(
+                "{key} = {} {}",
+                display(value),
+                format!("({source})").bright_black()
+            ),
+            None => println!("{key} {}", "(unset)".bright_black()),
+        }
+    }
+    Ok(())
+}
+
+/// Strings are shown without quotes, to be easily used in scripts
+fn display(value: &Value) -> String {
+    match value {
+        Value::String(value) => value.clone(),
+        value => value.to_string(),
+    }
+}
@@ -0,0 +1,318 @@
+use super::{Exit, HEADER_STYLE, STYLES};
+use crate::config::CONFIG_DIR_ENV;
+use anstyle::Style;
+use clap::{Command, ValueEnum};
+use clap_mangen::Man;
+use colored::Colorize;
+use neodojo::dojo::ASSIGNMENT_FILE;
+use neodojo::workspace::{STATE_DIR, WORKSPACE_ENV};
+use std::io::Write;
+use std::path::Path;
+
+/// Guides shown by `neodojo help <topic>` and appended to the man page
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Topic {
+    /// Files of an exercise and what neodojo does with them
+    Workspace,
+
+    /// What the exit status of neodojo means
+    ExitCodes,
+
+    /// Solutions to common problems
+    Troubleshooting,
+}
+
+impl Topic {
+    /// The guide, `# ` starting a section and backquotes marking literals
+    fn text(self) -> String {
+        match self {
+            Topic::Workspace => format!(
+                "\
+An exercise is a directory containing a `{ASSIGNMENT_FILE}`, found by walking up from the
+current directory, from `--path` or from `${WORKSPACE_ENV}`.
+
+# Files
+    {ASSIGNMENT_FILE}     the assignment, listing the protected files and the result
+                             container and volume
+    docker-compose.yml       the container the exercise is built and tested in
+    Dockerfile               the image of that container
+    src/Makefile             the build instructions
+    tests/                   the test suite
+    .neodojo.toml            settings of the workspace, see `neodojo config`
+    {STATE_DIR}/                state of neodojo, ignored by git
+
+The protected files must not be changed, `neodojo restore` brings them back to their
+version in the assignment. The updates of the assignment are pulled from the `upstream` remote
+added by `neodojo clone`, e.g. `git pull upstream main`, and are not taken for changes.
+
+# Makefile targets
+`neodojo test` runs them in the result container, from its `src` directory:
+    clean        removes the previous build
+    tests        builds the test suite, `CFLAGS:=` must be set on a single line
+    run_tests    runs the test suite, writing the results to the result volume
+
+`neodojo test --isolate` runs `run_tests` once per test with `RESULTS=<dir>`, the directory
+the results must be written to, and with the environment variables of googletest:
+`GTEST_LIST_TESTS=1` to list the tests, to be passed as `--gtest_list_tests`, and
+`GTEST_FILTER=<test>` to run one of them, which googletest reads by itself.
+
+# Results
+The test suite writes to the result volume:
+    test_detail.json    the googletest JSON report, the `results.tests` setting
+    memory.txt          the sanitizer report, the `results.memory` setting
+
+Other test frameworks are read from the same file, as set by the `testFramework` of the
+assignment or detected: `criterion` JSON, `catch2` JUnit XML, `cunit` XML, `unity` text or `tap`,
+the Test Anything Protocol.
+
+The tests taking longer than the `slowTestThreshold` of the assignment, in seconds, are
+reported as slow. `neodojo test --slowest N` shows the N slowest tests. `testFramework` and
+`slowTestThreshold` need `\"dojoAssignmentVersion\": 2`.
+
+The results of the last run are kept in `{STATE_DIR}/results`, `neodojo results` compares
+them with the ones of the grading pipeline.
+
+A summary of every run is added to `{STATE_DIR}/history.jsonl`, to flag the tests that
+regressed since the previous run and to show the progress with `neodojo history`."
+            ),
+            Topic::ExitCodes => format!(
+                "\
+The exit status of neodojo tells scripts how the command went:
+    {}    success, every test passed
+    {}    some tests failed, locally or in the grading pipeline
+    2    invalid usage, e.g. an unknown option
+    {}    the exercise does not build
+    {}    any other error, e.g. an invalid exercise or an unreachable GitLab",
+                Exit::Success as u8,
+                Exit::TestsFailed as u8,
+                Exit::BuildFailed as u8,
+                Exit::Error as u8,
+            ),
+            Topic::Troubleshooting => format!(
+                "\
+# No {ASSIGNMENT_FILE} found
+Run neodojo from within the exercise, or point to it with `--path` or `${WORKSPACE_ENV}`.
+
+# Unable to run the container runtime
+Check that docker runs with `docker info`, or set the `runtime` setting to a compatible
+runtime like `podman`.
+
+# The build or the tests never end
+Set a limit with `neodojo config set timeouts.test 60`, or `timeouts.build`.
+
+# A crash hides the results of the other tests
+Run `neodojo test --isolate` to run each test in a process of its own. The `run_tests` target
+must write to the directory of its `RESULTS` variable and pass `GTEST_LIST_TESTS` on, see
+`neodojo help workspace`, and the container must have `timeout` for `timeouts.test` to apply
+to each test.
+
+# Incorrect makefile format
+`src/Makefile` is protected, restore it with `neodojo restore`.
+
+# Missing results
+Check that the `run_tests` target writes to the files of the `results.tests` and
+`results.memory` settings, and run `neodojo test -vv` to see the output of the container.
+
+# Not logged in
+Run `neodojo login`, the credentials are kept in `${CONFIG_DIR_ENV}` or `~/.config/neodojo`."
+            ),
+        }
+    }
+}
+
+/// Show the help of a command, or a guide
+pub fn command(cmd: &Command, topic: &[String]) -> Exit {
+    let color = if colored::control::SHOULD_COLORIZE.should_colorize() {
+        clap::ColorChoice::Always
+    } else {
+        clap::ColorChoice::Never
+    };
+    let mut cmd = cmd.clone().color(color);
+    cmd.build();
+
+    if let [name] = topic {
+        if let Ok(topic) = Topic::from_str(name, true) {
+            print!("{}", render(&topic.text()));
+            return Exit::Success;
+        }
+    }
+
+    let mut help = &mut cmd;
+    for name in topic {
+        help = match help.find_subcommand_mut(name) {
+            Some(subcommand) => subcommand,
+            None => {
+                println!(
+                    "{}{} no command or topic named '{name}'",
+                    "error".red().bold(),
+                    ":".bold()
+                );
+                return Exit::Error;
+            }
+        };
+    }
+    let _ = help.print_help();
+
+    if topic.is_empty() {
+        print!("{}", render("# Topics"));
+        for topic in Topic::value_variants() {
+            let value = topic.to_possible_value().unwrap();
+            println!(
+                "  {}  {}",
+                paint(
+                    &STYLES.get_literal().bold(),
+                    &format!("{:<15}", value.get_name())
+                ),
+                value
+                    .get_help()
+                    .map(ToString::to_string)
+                    .unwrap_or_default()
+            );
+        }
+    }
+    Exit::Success
+}
+
+/// Write the man pages of neodojo and of its commands to `dir`, or the one of neodojo to the
+/// standard output
+pub fn man(cmd: &Command, dir: Option<&Path>) -> Exit {
+    let mut cmd = cmd.clone();
+    cmd.build();
+
+    let result = match dir {
+        Some(dir) => std::fs::create_dir_all(dir).and_then(|_| write_pages(&cmd, dir, "")),
+        None => page(&cmd, &mut std::io::stdout(), true),
+    };
+    match result {
+        Ok(()) => {
+            if let Some(dir) = dir {
+                println!(
+                    "{} man pages written to {}",
+                    "✔".green().bold(),
+                    dir.display().to_string().cyan()
+                );
+            }
+            Exit::Success
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+/// Write the page of `cmd` and of its subcommands, named like `neodojo-config-set.1`
+fn write_pages(cmd: &Command, dir: &Path, prefix: &str) -> std::io::Result<()> {
+    let name = format!("{prefix}{}", cmd.get_name());
+    let mut file = std::fs::File::create(dir.join(format!("{name}.1")))?;
+    page(cmd, &mut file, prefix.is_empty())?;
+
+    for subcommand in cmd.get_subcommands().filter(|sub| !sub.is_hide_set()) {
+        write_pages(subcommand, dir, &format!("{name}-"))?;
+    }
+    Ok(())
+}
+
+/// The man page of `cmd`, with the guides on the main page before its version
+fn page(cmd: &Command, out: &mut dyn Write, guides: bool) -> std::io::Result<()> {
+    let name = cmd
+        .get_bin_name()
+        .unwrap_or(cmd.get_name())
+        .replace(' ', "-");
+    let man = Man::new(cmd.clone().name(name));
+    if !guides {
+        return man.render(out);
+    }
+
+    man.render_title(out)?;
+    man.render_name_section(out)?;
+    man.render_synopsis_section(out)?;
+    man.render_description_section(out)?;
+    man.render_options_section(out)?;
+    man.render_subcommands_section(out)?;
+    for topic in Topic::value_variants() {
+        let title = topic
+            .to_possible_value()
+            .unwrap()
+            .get_name()
+            .replace('-', " ");
+        write!(
+            out,
+            ".SH \"{}\"\n{}",
+            title.to_uppercase(),
+            roff(&topic.text())
+        )?;
+    }
+    man.render_extra_section(out)?;
+    man.render_version_section(out)
+}
+
+/// Render a guide to the terminal, with the colors of the help
+fn render(text: &str) -> String {
+    let mut rendered = String::new();
+    for line in text.lines() {
+        match line.strip_prefix("# ") {
+            Some(title) => rendered += &format!("{}:\n", paint(&HEADER_STYLE, title)),
+            None => {
+                // Backquotes alternate between text and literals
+                for (i, part) in line.split('`').enumerate() {
+                    if i % 2 == 0 {
+                        rendered += part;
+                    } else {
+                        rendered += &paint(STYLES.get_literal(), part);
+                    }
+                }
+                rendered.push('\n');
+            }
+        }
+    }
+    rendered
+}
+
+/// Convert a guide to roff, its sections becoming subsections of the man page
+fn roff(text: &str) -> String {
+    let mut rendered = String::new();
+    let mut preformatted = false;
+    for line in text.lines() {
+        let escaped = line
+            .replace('\\', "\\\\")
+            .replace('-', "\\-")
+            .replace('`', "");
+
+        if let Some(title) = line.strip_prefix("# ") {
+            if preformatted {
+                rendered += ".fi\n";
+                preformatted = false;
+            }
+            rendered += &format!(".SS \"{}\"\n", title.replace('`', ""));
+        } else if line.starts_with("    ") {
+            if !preformatted {
+                rendered += ".nf\n";
+                preformatted = true;
+            }
+            rendered += &format!("\\&{escaped}\n");
+        } else {
+            if preformatted {
+                rendered += ".fi\n";
+                preformatted = false;
+            }
+            match line.is_empty() {
+                true => rendered += ".PP\n",
+                false => rendered += &format!("\\&{escaped}\n"),
+            }
+        }
+    }
+    if preformatted {
+        rendered += ".fi\n";
+    }
+    rendered
+}
+
+/// Paint `text` with a style of the help, when colors are enabled
+fn paint(style: &Style, text: &str) -> String {
+    if colored::control::SHOULD_COLORIZE.should_colorize() {
+        format!("{}{text}{}", style.render(), style.render_reset())
+    } else {
+        text.to_string()
+    }
+}
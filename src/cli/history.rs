@@ -0,0 +1,85 @@
+use super::submit::{ago, plural};
+use super::Exit;
+use colored::Colorize;
+use neodojo::history::{self, RunRecord};
+use std::path::Path;
+
+/// Width of the bar showing the share of the tests passed by a run
+const BAR_WIDTH: usize = 20;
+
+/// Show the number of tests passed by the last `limit` runs, the latest last
+pub fn command(root: &Path, limit: usize) -> Exit {
+    let runs = history::load(root);
+    if runs.is_empty() {
+        println!(
+            "{}",
+            "no run recorded yet, run `neodojo test` first".bright_black()
+        );
+        return Exit::Success;
+    }
+
+    let hidden = runs.len().saturating_sub(limit);
+    if hidden > 0 {
+        println!(
+            "{}",
+            format!("{} not shown", plural(hidden, "older run")).bright_black()
+        );
+    }
+
+    let mut previous = runs[..hidden].last();
+    for run in &runs[hidden..] {
+        let commit = match &run.commit {
+            Some(commit) => commit.get(..8).unwrap_or(commit),
+            None => "-",
+        };
+        let commit = if run.dirty {
+            format!("{commit}*")
+        } else {
+            commit.to_string()
+        };
+
+        let delta = previous.map_or(0, |previous| run.passed() as i64 - previous.passed() as i64);
+        let delta = match delta {
+            0 => String::new(),
+            delta if delta > 0 => format!(" {}", format!("+{delta}").green()),
+            delta => format!(" {}", delta.to_string().red()),
+        };
+        let memory = match run.memory.len() {
+            0 => String::new(),
+            count => format!(", {}", plural(count, "memory error").yellow()),
+        };
+
+        println!(
+            "{:<14}  {}  {}  {:>3}/{} passed{delta}{memory}",
+            ago(run.time()),
+            format!("{commit:<9}").yellow(),
+            bar(run),
+            run.passed(),
+            run.tests.len()
+        );
+        previous = Some(run);
+    }
+
+    if runs[hidden..].iter().any(|run| run.dirty) {
+        println!();
+        println!(
+            "{}",
+            "* uncommitted changes on top of the commit".bright_black()
+        );
+    }
+    Exit::Success
+}
+
+/// The share of the tests passed, failed and neither, e.g. skipped, told apart without colors too
+fn bar(run: &RunRecord) -> String {
+    let total = run.tests.len().max(1);
+    let width = |count: usize| (count * BAR_WIDTH + total / 2) / total;
+    let passed = width(run.passed());
+    let failed = width(run.failed()).min(BAR_WIDTH - passed);
+    format!(
+        "{}{}{}",
+        "█".repeat(passed).green(),
+        "▒".repeat(failed).red(),
+        "░".repeat(BAR_WIDTH - passed - failed).bright_black()
+    )
+}
@@ -1,22 +1,44 @@
+mod auth;
+mod check;
+mod clone;
+mod config;
+mod help;
+mod history;
+mod new;
+mod restore;
+mod results;
+mod submit;
 mod test;
 
+use crate::config::{ColorMode, Config, OutputFormat};
+use crate::logger;
+use crate::template::Language;
 use anstyle::{AnsiColor, Color::Ansi, Style};
 use clap::{builder::Styles, Command, CommandFactory, Parser, Subcommand, ValueHint};
 use clap_complete::{generate, Generator};
+use clap_verbosity_flag::InfoLevel;
+use colored::Colorize;
 use itertools::Itertools;
-use std::path::Path;
+use neodojo::workspace;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
 
 #[derive(Debug, Parser)]
 #[command(version, name = "neodojo")]
-#[command(about = "A better Dojo CLI")]
+#[command(about = "A better Dojo CLI", disable_help_subcommand = true)]
 #[command(after_help = "This is an experimental CLI tool, use at your own risk.")]
 #[command(styles=STYLES, help_template = help_template("help", false, true, true))]
 pub struct Cli {
     #[command(subcommand)]
     pub command: Commands,
 
+    /// `-v` shows the commands run, `-vv` their output too, `-q` only the final summary
     #[command(flatten)]
-    pub verbose: clap_verbosity_flag::Verbosity,
+    pub verbose: clap_verbosity_flag::Verbosity<InfoLevel>,
+
+    /// When to use colors [default: `color` setting]
+    #[arg(long, global = true, value_enum)]
+    pub color: Option<ColorMode>,
 }
 
 #[derive(Debug, Subcommand)]
@@ -25,10 +47,10 @@ pub enum Commands {
     #[command(subcommand_help_heading = "Exercise")]
     #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
     Test {
-        /// Path to the exercise directory
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
         #[arg(short, long, value_hint = ValueHint::DirPath)]
-        #[clap(short, long, default_value = "./")]
-        path: Box<Path>,
+        path: Option<Box<Path>>,
 
         /// Watch for changes and re-run tests automatically
         #[clap(short, long)]
@@ -40,6 +62,213 @@ pub enum Commands {
 
         #[clap(short, long, default_values_t = Vec::<String>::default())]
         filter: Vec<String>,
+
+        /// Show every failure of a test instead of only the first one
+        #[clap(long)]
+        all_failures: bool,
+
+        /// Run each test in a process of its own, so that a crash or a timeout only fails the
+        /// test concerned
+        #[clap(long)]
+        isolate: bool,
+
+        /// Number of tests run at once with `--isolate` [default: number of CPUs]
+        #[clap(short, long, requires = "isolate")]
+        jobs: Option<usize>,
+
+        /// Show the N slowest tests and the time spent in each phase of the run
+        #[clap(long, value_name = "N")]
+        slowest: Option<usize>,
+
+        /// Format of the results [default: `format` setting]
+        #[arg(long, value_enum)]
+        format: Option<OutputFormat>,
+    },
+
+    /// Commit the exercise with the results of the last run and push it
+    #[command(subcommand_help_heading = "Exercise")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Submit {
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        path: Option<Box<Path>>,
+
+        /// Message of the commit, the number of tests passed is appended to it
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// URL of the GitLab instance, whose login is used to push [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+    },
+
+    /// Show the number of tests passed by the previous runs
+    #[command(subcommand_help_heading = "Exercise")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    History {
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        path: Option<Box<Path>>,
+
+        /// Number of runs shown, the latest ones
+        #[arg(short = 'n', long, default_value_t = 20)]
+        limit: usize,
+    },
+
+    /// Create a new exercise from a template
+    #[command(subcommand_help_heading = "Exercise")]
+    #[command(styles=STYLES, help_template = help_template("command", true, true, false))]
+    New {
+        /// Directory of the new exercise, its name is used for the generated files
+        #[arg(value_hint = ValueHint::DirPath)]
+        name: Box<Path>,
+
+        /// Language of the built-in template
+        #[arg(short, long, value_enum, default_value_t = Language::C)]
+        language: Language,
+
+        /// Use a local template directory instead of a built-in one
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        template: Option<Box<Path>>,
+
+        /// Set a template variable, e.g. `--var service=exercise`
+        #[arg(long = "var", value_name = "KEY=VALUE", value_parser = new::parse_variable)]
+        variables: Vec<(String, String)>,
+    },
+
+    /// Validate the exercise configuration without running anything
+    #[command(subcommand_help_heading = "Exercise")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Check {
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        path: Option<Box<Path>>,
+    },
+
+    /// Restore the protected files of the exercise to their original version
+    #[command(subcommand_help_heading = "Exercise")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Restore {
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        path: Option<Box<Path>>,
+    },
+
+    /// Log in to GitLab with a personal access token or the OAuth device flow
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Login {
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Read the personal access token to log in with from stdin, instead of prompting for it
+        /// or reading $NEODOJO_GITLAB_TOKEN
+        #[arg(long, conflicts_with = "client_id")]
+        token_stdin: bool,
+
+        /// ID of the OAuth application to log in through with the device flow
+        #[arg(long)]
+        client_id: Option<String>,
+    },
+
+    /// Log out from GitLab, revoking the token
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Logout {
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+    },
+
+    /// Show the GitLab account you are logged in with
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Whoami {
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+    },
+
+    /// Show the results of the official grading pipeline
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Results {
+        /// Path to the exercise directory, or one of its subdirectories
+        /// [default: found from the current directory or $NEODOJO_WORKSPACE]
+        #[arg(short, long, value_hint = ValueHint::DirPath)]
+        path: Option<Box<Path>>,
+
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Show every failure of a test instead of only the first one
+        #[clap(long)]
+        all_failures: bool,
+    },
+
+    /// List the assignments available on GitLab
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", false, true, false))]
+    Fetch {
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Group the assignments are published in [default: `gitlab.group` setting]
+        #[arg(short, long)]
+        group: Option<String>,
+    },
+
+    /// Fork an assignment on GitLab and clone the fork
+    #[command(subcommand_help_heading = "GitLab")]
+    #[command(styles=STYLES, help_template = help_template("command", true, true, false))]
+    Clone {
+        /// Name of the assignment within its group, or its full path
+        assignment: String,
+
+        /// Directory to clone the exercise into [default: name of the assignment]
+        #[arg(value_hint = ValueHint::DirPath)]
+        directory: Option<Box<Path>>,
+
+        /// URL of the GitLab instance [default: `gitlab.host` setting]
+        #[arg(long)]
+        host: Option<String>,
+
+        /// Group the assignments are published in [default: `gitlab.group` setting]
+        #[arg(short, long)]
+        group: Option<String>,
+    },
+
+    /// Show or change the settings
+    #[command(subcommand_help_heading = "Misc", disable_help_subcommand = true)]
+    #[command(styles=STYLES, help_template = help_template("command", false, false, true))]
+    Config {
+        #[command(subcommand)]
+        action: ConfigAction,
+    },
+
+    /// Show the help of a command, or a guide on a topic
+    #[command(subcommand_help_heading = "Misc")]
+    #[command(styles=STYLES, help_template = help_template("command", true, false, false))]
+    Help {
+        /// Command, e.g. `config set`, or topic: workspace, exit-codes or troubleshooting
+        #[arg(value_name = "COMMAND|TOPIC")]
+        topic: Vec<String>,
+    },
+
+    /// Generate the man pages
+    #[command(subcommand_help_heading = "Misc")]
+    #[command(styles=STYLES, help_template = help_template("command", true, false, false))]
+    Man {
+        /// Directory to write a page per command to [default: the page of neodojo to stdout]
+        #[arg(value_hint = ValueHint::DirPath)]
+        dir: Option<Box<Path>>,
     },
 
     /// Upgrade neodojo to the latest version
@@ -59,24 +288,182 @@ pub enum Commands {
     },
 }
 
+#[derive(Debug, Subcommand)]
+pub enum ConfigAction {
+    /// Show the value of a setting
+    #[command(styles=STYLES, help_template = help_template("command", true, false, false))]
+    Get {
+        /// Name of the setting, e.g. `timeouts.test`
+        key: String,
+    },
+
+    /// Change a setting for the user, or for the workspace
+    #[command(styles=STYLES, help_template = help_template("command", true, true, false))]
+    Set {
+        /// Name of the setting, e.g. `timeouts.test`
+        key: String,
+
+        value: String,
+
+        /// Write the setting to the `.neodojo.toml` of the workspace
+        #[arg(short, long)]
+        workspace: bool,
+    },
+
+    /// Show every setting and where its value comes from
+    #[command(styles=STYLES, help_template = help_template("command", false, false, false))]
+    List,
+}
+
+/// How neodojo exits, described by `neodojo help exit-codes`. Invalid usage exits with 2, as
+/// reported by clap.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Exit {
+    Success = 0,
+    TestsFailed = 1,
+    BuildFailed = 3,
+    Error = 4,
+}
+
+impl From<Exit> for ExitCode {
+    fn from(exit: Exit) -> Self {
+        ExitCode::from(exit as u8)
+    }
+}
+
+impl Commands {
+    /// The workspace path given to the command, if it takes one
+    fn path(&self) -> Option<&Path> {
+        match self {
+            Commands::Test { path, .. }
+            | Commands::Submit { path, .. }
+            | Commands::History { path, .. }
+            | Commands::Check { path }
+            | Commands::Restore { path }
+            | Commands::Results { path, .. } => path.as_deref(),
+            _ => None,
+        }
+    }
+}
+
 impl Cli {
-    pub fn exec(&self) {
+    pub fn exec(&self) -> Exit {
         let mut cmd = Cli::command();
+        logger::init(self.verbose.log_level());
+        let workspace = workspace::discover(self.command.path()).ok();
+
+        // Managing the settings does not need them to be resolved
+        if let Commands::Config { action } = &self.command {
+            return config::command(action, workspace.as_deref());
+        }
+
+        let mut config = match Config::load(workspace.as_deref()) {
+            Ok(config) => config,
+            Err(err) => {
+                println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+                return Exit::Error;
+            }
+        };
+        if let Some(color) = self.color {
+            config.color = color;
+        }
+        config.apply_color();
+        let gitlab_host =
+            |host: &Option<String>| host.clone().unwrap_or_else(|| config.gitlab.host.clone());
+        let gitlab_group =
+            |group: &Option<String>| group.clone().or_else(|| config.gitlab.group.clone());
+
         match &self.command {
-            Commands::Test { path, filter, .. } => {
-                test::command(path, filter);
+            Commands::Test {
+                path,
+                filter,
+                all_failures,
+                isolate,
+                jobs,
+                slowest,
+                format,
+                ..
+            } => match workspace_root(path) {
+                Some(root) => {
+                    config.format = format.unwrap_or(config.format);
+                    let jobs = jobs.unwrap_or_else(|| {
+                        std::thread::available_parallelism().map_or(1, |jobs| jobs.get())
+                    });
+                    let isolate = isolate.then_some(jobs);
+                    test::command(&root, filter, *all_failures, isolate, *slowest, &config)
+                }
+                None => Exit::Error,
+            },
+            Commands::Submit {
+                path,
+                message,
+                host,
+            } => match workspace_root(path) {
+                Some(root) => submit::command(&root, &gitlab_host(host), message.as_deref()),
+                None => Exit::Error,
+            },
+            Commands::History { path, limit } => match workspace_root(path) {
+                Some(root) => history::command(&root, *limit),
+                None => Exit::Error,
+            },
+            Commands::New {
+                name,
+                language,
+                template,
+                variables,
+            } => new::command(name, *language, template.as_deref(), variables),
+            Commands::Check { path } => match workspace_root(path) {
+                Some(root) => check::command(&root),
+                None => Exit::Error,
+            },
+            Commands::Restore { path } => match workspace_root(path) {
+                Some(root) => restore::command(&root),
+                None => Exit::Error,
+            },
+            Commands::Login {
+                host,
+                token_stdin,
+                client_id,
+            } => auth::login(&gitlab_host(host), *token_stdin, client_id.as_deref()),
+            Commands::Logout { host } => auth::logout(&gitlab_host(host)),
+            Commands::Whoami { host } => auth::whoami(&gitlab_host(host)),
+            Commands::Results {
+                path,
+                host,
+                all_failures,
+            } => match workspace_root(path) {
+                Some(root) => results::command(&root, &gitlab_host(host), *all_failures, &config),
+                None => Exit::Error,
+            },
+            Commands::Fetch { host, group } => {
+                clone::fetch(&gitlab_host(host), gitlab_group(group).as_deref())
             }
-            Commands::Completion { shell } => print_completions(*shell, &mut cmd),
-            Commands::Upgrade { check } => {
+            Commands::Clone {
+                assignment,
+                directory,
+                host,
+                group,
+            } => clone::command(
+                &gitlab_host(host),
+                gitlab_group(group).as_deref(),
+                assignment,
+                directory.as_deref(),
+            ),
+            Commands::Config { .. } => unreachable!(),
+            Commands::Help { topic } => help::command(&cmd, topic),
+            Commands::Man { dir } => help::man(&cmd, dir.as_deref()),
+            Commands::Completion { shell } => {
+                print_completions(*shell, &mut cmd);
+                Exit::Success
+            }
+            Commands::Upgrade { .. } => {
                 println!("Checking for updates...");
-                if *check {
-                }
+                Exit::Success
             }
         }
     }
 }
 
-
 const HEADER_STYLE: Style = Style::new().bold().fg_color(Some(Ansi(AnsiColor::Green)));
 const STYLES: Styles = Styles::styled()
     .literal(AnsiColor::BrightCyan.on_default().bold())
@@ -91,7 +478,8 @@ fn help_template(
     let header = HEADER_STYLE.render();
     let rheader = HEADER_STYLE.render_reset();
 
-    let sections = [(
+    let sections = [
+        (
             format!("{header}Arguments{rheader}:\n{{positionals}}"),
             has_arguments,
         ),
@@ -102,7 +490,8 @@ fn help_template(
         (
             format!("{header}Commands{rheader}:\n{{subcommands}}"),
             has_commands,
-        )]
+        ),
+    ]
     .iter()
     .filter(|(_, has)| *has)
     .map(|(s, _)| s)
@@ -133,6 +522,16 @@ neodojo v{{version}}
     }
 }
 
+fn workspace_root(path: &Option<Box<Path>>) -> Option<PathBuf> {
+    match workspace::discover(path.as_deref()) {
+        Ok(root) => Some(root),
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            None
+        }
+    }
+}
+
 fn print_completions<G: Generator>(gen: G, cmd: &mut Command) {
     generate(gen, cmd, cmd.get_name().to_string(), &mut std::io::stdout());
 }
@@ -0,0 +1,180 @@
+use super::Exit;
+use crate::template::{Language, Template, TemplateError};
+use colored::Colorize;
+use itertools::Itertools;
+use neodojo::compose::{ComposeError, Environment, COMPOSE_FILE};
+use neodojo::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, thiserror::Error)]
+enum NewError {
+    #[error("destination '{0}' already exists and is not empty")]
+    Exists(PathBuf),
+
+    #[error("invalid exercise name '{0}'")]
+    InvalidName(String),
+
+    #[error("unable to render the template: {0}")]
+    Template(#[from] TemplateError),
+
+    #[error("the template produced an invalid dojo assignment: {0}")]
+    Assignment(#[from] DojoAssignmentError),
+
+    #[error("the template produced an invalid exercise environment: {0}")]
+    Compose(#[from] ComposeError),
+}
+
+pub fn command(
+    name: &Path,
+    language: Language,
+    template: Option<&Path>,
+    variables: &[(String, String)],
+) -> Exit {
+    match create(name, language, template, variables) {
+        Ok(files) => {
+            for file in files {
+                println!("    {} {}", "+".green(), file.display());
+            }
+            println!(
+                "{} created exercise {}, run `neodojo check -p {}` to validate it",
+                "✔".green().bold(),
+                name.display().to_string().cyan(),
+                name.display()
+            );
+            Exit::Success
+        }
+        Err(err) => {
+            if let NewError::Assignment(assignment) = &err {
+                assignment.pretty_print();
+            }
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+fn create(
+    destination: &Path,
+    language: Language,
+    template: Option<&Path>,
+    overrides: &[(String, String)],
+) -> Result<Vec<PathBuf>, NewError> {
+    let existed = destination.exists();
+    let not_empty = std::fs::read_dir(destination).is_ok_and(|mut dir| dir.next().is_some());
+    if not_empty {
+        return Err(NewError::Exists(destination.to_path_buf()));
+    }
+
+    let name = destination
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| NewError::InvalidName(destination.display().to_string()))?;
+
+    let mut variables = default_variables(&name);
+    variables.extend(overrides.iter().cloned());
+
+    let template = match template {
+        Some(dir) => Template::from_dir(dir)?,
+        None => Template::builtin(language),
+    };
+    let files = template.render(destination, &variables)?;
+
+    // An exercise that does not pass its own checks is not left behind
+    if let Err(err) = validate(destination) {
+        let _ = std::fs::remove_dir_all(destination);
+        if existed {
+            let _ = std::fs::create_dir(destination);
+        }
+        return Err(err);
+    }
+
+    Ok(files)
+}
+
+fn validate(root: &Path) -> Result<(), NewError> {
+    let assignment = DojoAssignment::try_from_file(&root.join(ASSIGNMENT_FILE))?;
+    Environment::load(&root.join(COMPOSE_FILE), &assignment.result)?;
+    Ok(())
+}
+
+/// Variables available to every template, derived from the name of the exercise
+fn default_variables(name: &str) -> BTreeMap<String, String> {
+    // A valid C identifier, for file and function names
+    let mut module = name
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect::<String>();
+    if module.starts_with(|c: char| c.is_ascii_digit()) {
+        module.insert(0, '_');
+    }
+
+    let suite = module
+        .split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| word[..1].to_uppercase() + &word[1..])
+        .join("");
+
+    BTreeMap::from([
+        ("name".to_string(), name.to_string()),
+        ("guard".to_string(), format!("{}_H", module.to_uppercase())),
+        ("module".to_string(), module),
+        ("suite".to_string(), suite),
+        ("service".to_string(), "exercise".to_string()),
+        ("volume".to_string(), "results".to_string()),
+    ])
+}
+
+/// Parse a `key=value` template variable
+pub fn parse_variable(variable: &str) -> Result<(String, String), String> {
+    variable
+        .split_once('=')
+        .map(|(key, value)| (key.trim().to_string(), value.to_string()))
+        .ok_or_else(|| format!("expected key=value, got '{variable}'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_exercises_are_removed() {
+        let dir = tempfile::tempdir().unwrap();
+        let template = dir.path().join("template");
+        std::fs::create_dir(&template).unwrap();
+        std::fs::write(
+            template.join(ASSIGNMENT_FILE),
+            r#"{ "version": "{{name}}" }"#,
+        )
+        .unwrap();
+        std::fs::write(template.join("main.c"), "int main;").unwrap();
+
+        let destination = dir.path().join("missing");
+        let result = create(&destination, Language::C, Some(&template), &[]);
+        assert!(matches!(result, Err(NewError::Assignment(_))));
+        assert!(!destination.exists());
+
+        // An empty destination is kept as it was
+        let destination = dir.path().join("empty");
+        std::fs::create_dir(&destination).unwrap();
+        let result = create(&destination, Language::C, Some(&template), &[]);
+        assert!(matches!(result, Err(NewError::Assignment(_))));
+        assert_eq!(std::fs::read_dir(&destination).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn builtin_templates_are_valid() {
+        let dir = tempfile::tempdir().unwrap();
+        for language in [Language::C, Language::Cpp] {
+            let destination = dir.path().join(format!("{language:?}"));
+            let files = create(&destination, language, None, &[]).unwrap();
+            assert!(files.contains(&destination.join(ASSIGNMENT_FILE)));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::Exit;
+use crate::git::{FileChange, Git, GitError};
+use crate::logger;
+use colored::Colorize;
+use neodojo::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use std::path::Path;
+
+#[derive(Debug, thiserror::Error)]
+pub(super) enum RestoreError {
+    #[error("invalid dojo assignment: {0}")]
+    Assignment(#[from] DojoAssignmentError),
+
+    #[error("{0}")]
+    Git(#[from] GitError),
+}
+
+pub fn command(root: &Path) -> Exit {
+    match restore(root) {
+        Ok(changes) if changes.is_empty() => {
+            println!("{} protected files are unchanged", "✔".green().bold());
+            Exit::Success
+        }
+        Ok(changes) => {
+            let restored = changes
+                .iter()
+                .any(|(change, _)| *change != FileChange::Untracked);
+            for (change, path) in changes {
+                if change == FileChange::Untracked {
+                    println!(
+                        "    {} {path} {}",
+                        change.symbol(),
+                        "(untracked, left untouched)".bright_black().italic()
+                    );
+                } else {
+                    println!("    {} {path}", change.symbol());
+                }
+            }
+            if restored {
+                println!("{} protected files restored", "✔".green().bold());
+            }
+            Exit::Success
+        }
+        Err(err) => {
+            if let RestoreError::Assignment(assignment) = &err {
+                assignment.pretty_print();
+            }
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+pub(super) fn restore(root: &Path) -> Result<Vec<(FileChange, String)>, RestoreError> {
+    let assignment = DojoAssignment::try_from_file(&root.join(ASSIGNMENT_FILE))?;
+    let (commit, changes) = immutable_changes(root, &assignment)?;
+    if changes
+        .iter()
+        .any(|(change, _)| *change != FileChange::Untracked)
+    {
+        Git::new(root).restore(&commit, &assignment.immutable_paths())?;
+    }
+
+    Ok(changes)
+}
+
+/// The immutable files of the assignment changed in the exercise, along with the commit of the
+/// assignment they can be restored from
+pub(super) fn immutable_changes(
+    root: &Path,
+    assignment: &DojoAssignment,
+) -> Result<(String, Vec<(FileChange, String)>), GitError> {
+    let git = Git::new(root);
+    let commit = git.assignment_commit()?;
+    let paths = assignment.immutable_paths();
+    if paths.is_empty() {
+        return Ok((commit, vec![]));
+    }
+
+    let changes = git.changes_since(&commit, &paths)?;
+    Ok((commit, changes))
+}
+
+/// Warn about changed immutable files, the official grading runs with the original ones. The
+/// warning goes to stderr, apart from the results other tools may read.
+pub fn warn_immutable_changes(root: &Path, assignment: &DojoAssignment) {
+    if logger::quiet() || !Git::new(root).is_repository() {
+        return;
+    }
+
+    let changes = match immutable_changes(root, assignment) {
+        Ok((_, changes)) if !changes.is_empty() => changes,
+        _ => return,
+    };
+
+    eprintln!(
+        "{}{} protected files were changed, the official grading will use their original version:",
+        "warning".yellow().bold(),
+        ":".bold()
+    );
+    for (change, path) in changes {
+        eprintln!("    {}", format!("{} {path}", change.symbol()).yellow());
+    }
+    eprintln!(
+        "{}",
+        "    run `neodojo restore` to restore them".bright_black()
+    );
+    eprintln!();
+}
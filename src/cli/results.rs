@@ -0,0 +1,227 @@
+use super::auth::{self, AuthError};
+use super::test::{last_report, print_memory_report, wrap_progress};
+use super::Exit;
+use crate::config::Config;
+use crate::git::{Git, GitError};
+use crate::gitlab::{self, GitLabError, Pipeline};
+use colored::Colorize;
+use neodojo::compose::{Environment, COMPOSE_FILE};
+use neodojo::dojo::{DojoAssignment, ASSIGNMENT_FILE};
+use neodojo::gunit::TestError;
+use neodojo::paths::PathMapper;
+use neodojo::pipeline::{read_results, ResultFiles};
+use neodojo::render::PrintOptions;
+use neodojo::report::{RunReport, Status};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// The remote the exercise was cloned from
+const REMOTE: &str = "origin";
+
+#[derive(Debug, thiserror::Error)]
+enum ResultsError {
+    #[error("{0}")]
+    Auth(#[from] AuthError),
+
+    #[error("{0}")]
+    GitLab(#[from] GitLabError),
+
+    #[error("{0}")]
+    Git(#[from] GitError),
+
+    #[error("remote '{REMOTE}' ({0}) is not a GitLab project")]
+    NotAProject(String),
+
+    #[error("no branch checked out")]
+    DetachedHead,
+
+    #[error("no pipeline found for branch '{0}', push your work to run one")]
+    NoPipeline(String),
+
+    #[error("pipeline #{0} is still {1}, try again later")]
+    Pending(u64, String),
+
+    #[error("pipeline #{0} produced no test results")]
+    NoResults(u64),
+
+    #[error("unable to store the results: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("unable to read the results: {0}")]
+    Test(#[from] TestError),
+}
+
+pub fn command(root: &Path, host: &str, all_failures: bool, config: &Config) -> Exit {
+    let assignment = DojoAssignment::try_from_file(&root.join(ASSIGNMENT_FILE)).ok();
+    let paths = assignment
+        .as_ref()
+        .and_then(|assignment| Environment::load(&root.join(COMPOSE_FILE), &assignment.result).ok())
+        .map_or_else(
+            || PathMapper::new(root),
+            |environment| environment.path_mapper(),
+        );
+    let options = PrintOptions {
+        paths,
+        all_failures,
+        previous: None,
+        slowest: None,
+        slow_threshold: assignment
+            .as_ref()
+            .and_then(|assignment| assignment.slow_test_threshold),
+    };
+
+    let result = wrap_progress("Fetching pipeline results", || {
+        fetch(root, host, assignment.as_ref(), &config.results)
+    })
+    .and_then(|(pipeline, results)| {
+        print_pipeline(root, &pipeline);
+        print_memory_report(&results, &config.results, &options.paths);
+        let framework = assignment
+            .as_ref()
+            .and_then(|assignment| assignment.test_framework);
+        Ok(read_results(&results, &config.results, framework)?)
+    });
+
+    match result {
+        Ok(remote) => {
+            remote.pretty_print(&options);
+            println!();
+            compare(root, &remote);
+            if remote.has_failed() {
+                Exit::TestsFailed
+            } else {
+                Exit::Success
+            }
+        }
+        Err(err) => {
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+/// Download the results of the latest pipeline of the current branch
+fn fetch(
+    root: &Path,
+    host: &str,
+    assignment: Option<&DojoAssignment>,
+    files: &ResultFiles,
+) -> Result<(Pipeline, PathBuf), ResultsError> {
+    let git = Git::new(root);
+    let branch = git.current_branch()?.ok_or(ResultsError::DetachedHead)?;
+    let remote = git.remote_url(REMOTE)?;
+    let project = gitlab::project_path(&remote).ok_or(ResultsError::NotAProject(remote))?;
+
+    let gitlab = auth::client(host)?;
+    let pipeline = gitlab
+        .latest_pipeline(&project, &branch)?
+        .ok_or_else(|| ResultsError::NoPipeline(branch.clone()))?;
+
+    // The results are written to the result volume, which CI keeps as artifacts
+    let mut directories = vec![String::new()];
+    if let Some(volume) = assignment.and_then(|assignment| assignment.result.volume.as_ref()) {
+        directories.insert(0, format!("{volume}/"));
+    }
+
+    let results = tempfile::tempdir()?.into_path();
+    for job in gitlab.pipeline_jobs(&project, pipeline.id)? {
+        if job.artifacts_file.is_none() {
+            continue;
+        }
+
+        for directory in &directories {
+            let path = format!("{directory}{}", files.tests);
+            let Some(content) = gitlab.job_artifact(&project, job.id, &path)? else {
+                continue;
+            };
+            std::fs::write(results.join(&files.tests), content)?;
+
+            let path = format!("{directory}{}", files.memory);
+            if let Some(content) = gitlab.job_artifact(&project, job.id, &path)? {
+                std::fs::write(results.join(&files.memory), content)?;
+            }
+            return Ok((pipeline, results));
+        }
+    }
+
+    match pipeline.status.as_str() {
+        "created" | "waiting_for_resource" | "preparing" | "pending" | "running" => {
+            Err(ResultsError::Pending(pipeline.id, pipeline.status))
+        }
+        _ => Err(ResultsError::NoResults(pipeline.id)),
+    }
+}
+
+fn print_pipeline(root: &Path, pipeline: &Pipeline) {
+    let status = match pipeline.status.as_str() {
+        "success" => pipeline.status.green(),
+        "failed" | "canceled" => pipeline.status.red(),
+        _ => pipeline.status.yellow(),
+    };
+    println!(
+        "pipeline #{} {status} on commit {}",
+        pipeline.id,
+        pipeline.sha.get(..8).unwrap_or(&pipeline.sha)
+    );
+    println!("{}", pipeline.web_url.bright_black());
+
+    if Git::new(root).head().is_ok_and(|head| head != pipeline.sha) {
+        println!(
+            "{}{} the pipeline did not run on the commit checked out",
+            "warning".yellow().bold(),
+            ":".bold()
+        );
+    }
+    println!();
+}
+
+/// Show the tests whose outcome differs between the last local run and the pipeline
+fn compare(root: &Path, remote: &RunReport) {
+    let remote = outcomes(remote);
+    let Some(local) = last_report(root).map(|report| outcomes(&report)) else {
+        println!(
+            "{}",
+            "run `neodojo test` to compare with the local results".bright_black()
+        );
+        return;
+    };
+
+    let outcome = |status: Option<&Status>| match status {
+        Some(status) => status.colored(),
+        None => "not run".yellow(),
+    };
+
+    let mut names = local.keys().chain(remote.keys()).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    let differences = names
+        .into_iter()
+        .filter(|name| local.get(*name) != remote.get(*name))
+        .collect::<Vec<_>>();
+
+    if differences.is_empty() {
+        println!(
+            "{} the last local run has the same results",
+            "✔".green().bold()
+        );
+        return;
+    }
+
+    println!("differences with the last local run:");
+    for name in differences {
+        println!(
+            "    test {name} ... local {}, remote {}",
+            outcome(local.get(name)),
+            outcome(remote.get(name))
+        );
+    }
+}
+
+/// How each test ended, by its full name
+fn outcomes(report: &RunReport) -> BTreeMap<String, Status> {
+    report
+        .tests
+        .iter()
+        .map(|test| (test.full_name(), test.status))
+        .collect()
+}
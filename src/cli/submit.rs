@@ -0,0 +1,243 @@
+use super::restore::immutable_changes;
+use super::test::{last_report, last_tree};
+use super::{auth, Exit};
+use crate::git::{FileChange, Git, GitError};
+use colored::Colorize;
+use neodojo::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use neodojo::report::RunReport;
+use neodojo::workspace::{self, STATE_DIR};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// File of the state directory recording the tests passed by each commit
+const PROGRESS_FILE: &str = "progress.json";
+
+/// The remote the work is pushed to when the branch is not tracked yet
+const REMOTE: &str = "origin";
+
+#[derive(Debug, thiserror::Error)]
+enum SubmitError {
+    #[error("{0}")]
+    Git(#[from] GitError),
+
+    #[error("invalid dojo assignment: {0}")]
+    Assignment(#[from] DojoAssignmentError),
+
+    #[error("no test results, run `neodojo test` first")]
+    NoResults,
+
+    #[error("the exercise changed since the tests were run, run `neodojo test` first")]
+    OutdatedResults,
+
+    #[error("protected files were changed: {0}, run `neodojo restore` and remove the new ones")]
+    ProtectedChanges(String),
+
+    #[error(
+        "new files were not committed: {0}, add them with `git add` or ignore them in .gitignore"
+    )]
+    NewFiles(String),
+}
+
+/// Number of tests passed by the committed versions of the exercise, by commit
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Progress {
+    commits: BTreeMap<String, u32>,
+}
+
+impl Progress {
+    fn load(root: &Path) -> Self {
+        std::fs::read_to_string(root.join(STATE_DIR).join(PROGRESS_FILE))
+            .ok()
+            .and_then(|file| serde_json::from_str(&file).ok())
+            .unwrap_or_default()
+    }
+
+    fn record(root: &Path, commit: String, passed: u32) {
+        let mut progress = Self::load(root);
+        progress.commits.insert(commit, passed);
+        if let (Ok(dir), Ok(json)) = (
+            workspace::state_dir(root),
+            serde_json::to_string_pretty(&progress),
+        ) {
+            let _ = std::fs::write(dir.join(PROGRESS_FILE), json);
+        }
+    }
+}
+
+/// Remind to commit and push the work after the tests were run
+pub fn remind(root: &Path, report: &RunReport) {
+    let git = Git::new(root);
+    if !git.is_repository() {
+        return;
+    }
+
+    let passed = report.passed() as u32;
+    let changes = git.uncommitted_changes().unwrap_or_default();
+    let head = git.head().ok();
+
+    // A clean working tree is exactly the last commit
+    if let Some(head) = head.clone().filter(|_| changes.is_empty()) {
+        Progress::record(root, head, passed);
+    }
+
+    let upstream = git.upstream();
+    let unpushed = upstream
+        .as_ref()
+        .and_then(|upstream| git.unpushed_commits(upstream).ok())
+        .unwrap_or_default();
+
+    let mut state = Vec::new();
+    if !changes.is_empty() {
+        state.push(plural(changes.len(), "uncommitted change"));
+    }
+    match &upstream {
+        Some(_) if unpushed > 0 => state.push(format!(
+            "{} not pushed",
+            plural(unpushed as usize, "commit")
+        )),
+        Some(_) => {}
+        None => state.push("branch never pushed".to_string()),
+    }
+    if state.is_empty() {
+        return;
+    }
+    if let Some(time) = upstream.and_then(|upstream| git.last_push(&upstream)) {
+        state.push(format!("last push {}", ago(time)));
+    }
+
+    println!();
+    println!(
+        "{}{} {}",
+        "git".yellow().bold(),
+        ":".bold(),
+        state.join(", ")
+    );
+
+    let committed = head.and_then(|head| Progress::load(root).commits.get(&head).copied());
+    match committed {
+        Some(committed) if !changes.is_empty() && passed > committed => println!(
+            "{}{} {passed} tests pass now against {committed} in the last commit, \
+             run `neodojo submit` to save your progress",
+            "reminder".yellow().bold(),
+            ":".bold()
+        ),
+        _ => println!(
+            "{}",
+            "run `neodojo submit` to commit and push your work".bright_black()
+        ),
+    }
+}
+
+/// Commit the work with the results of the last run and push it
+pub fn command(root: &Path, host: &str, message: Option<&str>) -> Exit {
+    match submit(root, host, message) {
+        Ok(()) => Exit::Success,
+        Err(err) => {
+            if let SubmitError::Assignment(assignment) = &err {
+                assignment.pretty_print();
+            }
+            println!("{}{} {}", "error".red().bold(), ":".bold(), err);
+            Exit::Error
+        }
+    }
+}
+
+fn submit(root: &Path, host: &str, message: Option<&str>) -> Result<(), SubmitError> {
+    // The official grading would use the original protected files anyway
+    let assignment = DojoAssignment::try_from_file(&root.join(ASSIGNMENT_FILE))?;
+    let (_, changes) = immutable_changes(root, &assignment)?;
+    if !changes.is_empty() {
+        let paths = changes
+            .into_iter()
+            .map(|(_, path)| path)
+            .collect::<Vec<_>>();
+        return Err(SubmitError::ProtectedChanges(paths.join(", ")));
+    }
+
+    // The results are committed along with the exercise they are the results of
+    let report = last_report(root).ok_or(SubmitError::NoResults)?;
+    let git = Git::new(root);
+    if last_tree(root) != Some(git.worktree_tree()?) {
+        return Err(SubmitError::OutdatedResults);
+    }
+    let passed = report.passed() as u32;
+    let summary = format!("{passed}/{} tests passed", report.tests.len());
+
+    if !git.uncommitted_changes()?.is_empty() {
+        let message = match message {
+            Some(message) => format!("{message}\n\n{summary}"),
+            None => format!("Submit: {summary}"),
+        };
+        // New files are only committed once the user agreed, they may be anything
+        let added = git.untracked_files()?;
+        if !added.is_empty() && !confirm_new_files(&added) {
+            return Err(SubmitError::NewFiles(added.join(", ")));
+        }
+        git.commit(&message, &added)?;
+        Progress::record(root, git.head()?, passed);
+        println!("{} committed ({summary})", "✔".green().bold());
+    }
+
+    let unpushed = match git.upstream() {
+        Some(upstream) => git.unpushed_commits(&upstream)? > 0,
+        None => true,
+    };
+    if !unpushed {
+        println!("{} nothing to submit", "✔".green().bold());
+        return Ok(());
+    }
+
+    let header = auth::client(host)
+        .ok()
+        .and_then(|gitlab| gitlab.git_auth_header());
+    git.push(REMOTE, header.as_deref())?;
+    println!("{} pushed", "✔".green().bold());
+    Ok(())
+}
+
+/// List the new files of the exercise and ask whether to commit them, never when stdin is not
+/// a terminal to answer from
+fn confirm_new_files(files: &[String]) -> bool {
+    println!("New files:");
+    for file in files {
+        println!(
+            "    {}",
+            format!("{} {file}", FileChange::Untracked.symbol()).cyan()
+        );
+    }
+    if !std::io::stdin().is_terminal() {
+        return false;
+    }
+
+    print!("Commit them too? [y/N] ");
+    let _ = std::io::stdout().flush();
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer).is_ok()
+        && matches!(answer.trim(), "y" | "Y" | "yes")
+}
+
+pub fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// How long ago `time` was, roughly
+pub fn ago(time: SystemTime) -> String {
+    let seconds = SystemTime::now()
+        .duration_since(time)
+        .unwrap_or_default()
+        .as_secs();
+
+    match seconds {
+        0..=59 => "just now".to_string(),
+        60..=3599 => format!("{} ago", plural(seconds as usize / 60, "minute")),
+        3600..=86399 => format!("{} ago", plural(seconds as usize / 3600, "hour")),
+        _ => format!("{} ago", plural(seconds as usize / 86400, "day")),
+    }
+}
@@ -1,79 +1,123 @@
-use crate::asan::Asan;
-use crate::dojo::DojoAssignment;
-use crate::gunit::{TestError, UnitTest};
-use crate::sarif::BuildDiagnostic;
+use super::Exit;
+use crate::config::{Config, OutputFormat};
+use crate::git::Git;
+use crate::logger;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use serde_sarif::sarif::Sarif;
-use std::io::{BufRead, Write};
-use std::path::{Path, PathBuf};
-use std::process::Output;
+use neodojo::asan::Asan;
+use neodojo::history::{self, RunRecord};
+use neodojo::paths::PathMapper;
+use neodojo::pipeline::{BuildError, Exercise, Pipeline, PipelineError, ResultFiles};
+use neodojo::render::PrintOptions;
+use neodojo::report::{PhaseKind, RunReport, Status};
+use neodojo::workspace::{self, STATE_DIR};
+use std::path::Path;
 use std::time::Duration;
 
-const DOCKER_COMPOSE: &str = "docker-compose.yml";
-const TEST_RESULTS_FILE: &str = "test_detail.json";
-const ASAN_FILE: &str = "memory.txt";
-const DOJO_ASSIGNMENT_FILE: &str = "dojo_assignment.json";
+/// Subdirectory of the state directory keeping the results of the last local run
+pub const LAST_RESULTS_DIR: &str = "results";
 
-#[derive(thiserror::Error, Debug)]
-enum RunError {
-    // #[error("unable to run docker-compose")]
-    // DockerCompose,
-    #[error("invalid dojo workspace: {0}")]
-    DojoWorkspace(PathBuf),
+/// File of the last results directory with the report of the run, whatever the test framework
+const LAST_REPORT_FILE: &str = "report.json";
 
-    #[error("error building the project")]
-    Build(#[from] BuildError),
+/// File of the last results directory with the git tree of the exercise tested, see
+/// [`Git::worktree_tree`]
+const LAST_TREE_FILE: &str = "tree";
 
-    #[error("error running tests: {0}")]
-    Test(#[from] TestError),
-}
-
-#[derive(Debug, thiserror::Error)]
-pub enum BuildError {
-    #[error("incorrect makefile format: {0}")]
-    IncompatibleMakefile(String),
-
-    #[error("build failed")]
-    BuildFailed(BuildDiagnostic),
-}
+pub fn command(
+    root: &Path,
+    _filter: &[String],
+    all_failures: bool,
+    isolate: Option<usize>,
+    slowest: Option<usize>,
+    config: &Config,
+) -> Exit {
+    let mut options = PrintOptions {
+        paths: PathMapper::new(root),
+        all_failures,
+        previous: history::last(root),
+        slowest,
+        slow_threshold: None,
+    };
+
+    let pipeline = Pipeline::new(root)
+        .runtime(&config.runtime)
+        .build_timeout(config.timeouts.build())
+        .test_timeout(config.timeouts.test())
+        .result_files(config.results.clone())
+        .isolate(isolate);
+
+    let mut report = RunReport::default();
+    let result = pipeline.prepare().and_then(|exercise| {
+        super::restore::warn_immutable_changes(root, exercise.assignment());
+        options.paths = exercise.paths().clone();
+        options.slow_threshold = exercise.assignment().slow_test_threshold;
+        run(&exercise, config, &mut report)
+    });
+    if let Err(PipelineError::Build(BuildError::BuildFailed(diagnostics))) = &result {
+        report.diagnostics = diagnostics.clone();
+    }
 
-pub fn command(root: &Path, filter: &Vec<String>) {
-    let result = run(root);
-    match result {
-        Ok(test) => {
-            test.pretty_print();
-        },
-        Err(err) => {
-            match &err {
-                RunError::Build(b) =>
-                {
-                    #[allow(irrefutable_let_patterns)]
-                    if let BuildError::BuildFailed(diagnostics) = b {
-                        diagnostics.pretty_print();
-                    }
-                }
-                RunError::Test(t) => {
-                    if let TestError::TestFailed(test) = t {
-                        test.pretty_print();
-                    }
-                }
+    match (&result, config.format) {
+        // Only the results are printed as JSON or XML, for other tools to read them
+        (_, OutputFormat::Json) => println!("{}", report.to_json()),
+        (_, OutputFormat::Junit) => print!("{}", report.to_junit()),
+        (_, OutputFormat::Tap) => print!("{}", report.to_tap()),
+        (Ok(()), OutputFormat::Pretty) if logger::quiet() => report.print_summary(),
+        (Ok(()), OutputFormat::Pretty) => report.pretty_print(&options),
+        (Err(err), OutputFormat::Pretty) => {
+            match err {
+                PipelineError::Assignment(assignment) => assignment.pretty_print(),
+                PipelineError::Build(BuildError::BuildFailed(_)) => report.print_diagnostics(),
                 _ => {}
             }
-        
+
             println!("{}{} {}", "error".red().bold(), ":".bold(), err);
         }
     }
+    // Apart from the results, for the tools reading them
+    if let (Err(err), false) = (&result, config.format == OutputFormat::Pretty) {
+        eprintln!("{}{} {}", "error".red().bold(), ":".bold(), err);
+    }
+
+    if result.is_ok()
+        && config.reminders
+        && config.format == OutputFormat::Pretty
+        && !logger::quiet()
+    {
+        super::submit::remind(root, &report);
+    }
+
+    match result {
+        Ok(()) if report.has_failed() => Exit::TestsFailed,
+        Ok(()) => Exit::Success,
+        Err(PipelineError::Build(BuildError::BuildFailed(_))) => Exit::BuildFailed,
+        Err(_) => Exit::Error,
+    }
 }
 
-fn wrap_progress<F, T, E>(message: &str, f: F) -> Result<T, E>
+pub fn wrap_progress<F, T, E>(message: &str, f: F) -> Result<T, E>
 where
-    F: Fn() -> Result<T, E>,
+    F: FnOnce() -> Result<T, E>,
 {
     let style = ProgressStyle::with_template("{spinner:.bold.cyan} {wide_msg}")
         .unwrap()
         .tick_chars("⠋⠙⠹⠸⠼⠴⠦⠧⠇⠏ ");
 
+    // The spinner would be drawn over the logged commands
+    if logger::quiet() || logger::verbose() {
+        let res = f();
+        if !logger::quiet() {
+            let mark = if res.is_ok() {
+                "✔".green()
+            } else {
+                "✖".red()
+            };
+            eprintln!("{} {message}", mark.bold());
+        }
+        return res;
+    }
+
     let bar = ProgressBar::new_spinner()
         .with_style(style)
         .with_message(message.to_string());
@@ -96,180 +140,100 @@ where
     res
 }
 
-fn run(root: &Path) -> Result<UnitTest, RunError> {
-    let assignment = DojoAssignment::try_from_file(&root.join(DOJO_ASSIGNMENT_FILE))
-        .map_err(|_| RunError::DojoWorkspace(root.to_path_buf()))?;
-    assert_ne!(assignment.result.volume, None);
-    let container_name = assignment.result.container.as_str();
-
-    let tempdir = tempfile::tempdir().unwrap().into_path();
-    let overrides = create_docker_compose_file(tempdir.to_str().unwrap(), container_name);
-    let args = vec![
-        "compose",
-        // "--project-name",
-        // PROJECT_NAME,
-        "--file",
-        DOCKER_COMPOSE,
-        "--file",
-        &overrides,
-    ];
-
-    wrap_progress("Setting up environment", || {
-        exec_run(root, container_name, &args)
-    })
-    .unwrap();
-
-    wrap_progress("Cleaning up", || exec_clean(root, container_name, &args)).unwrap();
+/// Run every step of the exercise, recording them in `report`
+fn run(exercise: &Exercise, config: &Config, report: &mut RunReport) -> Result<(), PipelineError> {
+    report.phase(PhaseKind::Setup, || {
+        wrap_progress("Setting up environment", || exercise.start())
+    })?;
 
-    let diagnostics = wrap_progress("Building project", || {
-        exec_build(root, container_name, &args)
+    report.phase(PhaseKind::Clean, || {
+        wrap_progress("Cleaning up", || exercise.clean())
     })?;
-    diagnostics.pretty_print();
 
-    wrap_progress("Running tests", || {
-        exec_test(
-            root,
-            container_name,
-            &args,
-            &tempdir.join(TEST_RESULTS_FILE),
-        )
-    })
-    .map_err(RunError::from)
-}
+    report.diagnostics = report.phase(PhaseKind::Build, || {
+        wrap_progress("Building project", || exercise.build())
+    })?;
+    if !logger::quiet() && config.format == OutputFormat::Pretty {
+        report.print_diagnostics();
+    }
 
-fn create_docker_compose_file(dir: &str, container_name: &str) -> String {
-    let compose = format!(
-        r#"services:
-    {container_name}:
-        entrypoint:
-            - sleep
-            - infinity
-        environment:
-            - IN_DOCKER=true
-        volumes: !override
-            - {dir}:/results/
-        "#,
-        container_name = container_name,
-        dir = dir
+    let result = report.phase(PhaseKind::Test, || {
+        wrap_progress("Running tests", || exercise.test())
+    });
+    // The sanitizer report of a crash is shown along with the test that crashed
+    let crashed = result
+        .as_ref()
+        .is_ok_and(|results| results.count(Status::Crash) > 0);
+    if config.format == OutputFormat::Pretty && !crashed {
+        print_memory_report(exercise.results_dir(), &config.results, exercise.paths());
+    }
+    let result = result.map(|results| report.merge(results));
+    save_results(
+        exercise.pipeline().root(),
+        exercise.results_dir(),
+        &config.results,
+        result.is_ok().then_some(&*report),
     );
-
-    let path = format!("{dir}/docker-compose.yml");
-    let mut compose_file = std::fs::File::create(&path).unwrap();
-    compose_file.write_all(compose.as_bytes()).unwrap();
-    path
-}
-
-fn exec_run(
-    root: &Path,
-    container_name: &str,
-    common_args: &[&str],
-) -> Result<Output, std::io::Error> {
-    std::process::Command::new("docker")
-        .args(common_args)
-        .arg("run")
-        .arg("-d")
-        .arg("--rm")
-        .arg("--build")
-        .arg(container_name)
-        .current_dir(root)
-        .output()
+    if result.is_ok() {
+        record_history(exercise.pipeline().root(), report);
+    }
+    Ok(result?)
 }
 
-fn exec_clean(
-    root: &Path,
-    container_name: &str,
-    common_args: &[&str],
-) -> Result<Output, std::io::Error> {
-    std::process::Command::new("docker")
-        .args(common_args)
-        .arg("exec")
-        .arg(container_name)
-        .arg("make")
-        .arg("-C")
-        .arg("src")
-        .arg("clean")
-        .arg("-s")
-        .current_dir(root)
-        .output()
+/// Keep the results of the run, to be compared with the official ones
+fn save_results(root: &Path, results: &Path, files: &ResultFiles, report: Option<&RunReport>) {
+    let Ok(dir) = workspace::state_dir(root).map(|dir| dir.join(LAST_RESULTS_DIR)) else {
+        return;
+    };
+    let _ = std::fs::remove_dir_all(&dir);
+    if std::fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    for file in [&files.tests, &files.memory] {
+        let _ = std::fs::copy(results.join(file), dir.join(file));
+    }
+    if let Some(report) = report {
+        let _ = std::fs::write(dir.join(LAST_REPORT_FILE), report.to_json());
+        if let Ok(tree) = Git::new(root).worktree_tree() {
+            let _ = std::fs::write(dir.join(LAST_TREE_FILE), tree);
+        }
+    }
 }
 
-fn exec_build(
-    root: &Path,
-    container_name: &str,
-    common_args: &[&str],
-) -> Result<BuildDiagnostic, BuildError> {
-    let makefile = std::fs::read_to_string(root.join("src/Makefile"))
-        .map_err(|_| BuildError::IncompatibleMakefile("unable to read Makefile".to_string()))?;
-
-    let (_, cflags) = makefile
-        .lines()
-        .find(|line| line.starts_with("CFLAGS:="))
-        .ok_or(BuildError::IncompatibleMakefile("missing CFLAGS".to_string()))?
-        .split_once('=')
-        .ok_or(BuildError::IncompatibleMakefile("missing CFLAGS".to_string()))?;
-
-    let build = std::process::Command::new("docker")
-        .args(common_args)
-        .arg("exec")
-        .arg(container_name)
-        .arg("make")
-        .arg("-C")
-        .arg("src")
-        .arg("tests")
-        .arg(format!("CFLAGS={cflags} -fdiagnostics-format=sarif-stderr"))
-        .current_dir(root)
-        .output()
-        .unwrap();
-
-    let mut diagnostics = BuildDiagnostic::default();
-    for report in build.stderr.lines() {
-        let sarif: Sarif = match serde_json::from_str(&report.unwrap()) {
-            Ok(sarif) => sarif,
-            Err(_) => continue,
-        };
-
-        diagnostics += BuildDiagnostic::from(sarif);
+/// Add the run to the history of the workspace, along with the commit it tested
+fn record_history(root: &Path, report: &RunReport) {
+    let git = Git::new(root);
+    let commit = git.head().ok();
+    let dirty = commit.is_some() && !git.uncommitted_changes().unwrap_or_default().is_empty();
+    if let Err(err) = history::record(root, &RunRecord::new(report, commit, dirty)) {
+        log::debug!("unable to record the run in the history: {err}");
     }
+}
 
-    if diagnostics.has_errors() {
-        Err(BuildError::BuildFailed(diagnostics))
-    } else {
-        Ok(diagnostics)
-    }
+/// The report of the last local run, see [`save_results`]
+pub fn last_report(root: &Path) -> Option<RunReport> {
+    let path = root
+        .join(STATE_DIR)
+        .join(LAST_RESULTS_DIR)
+        .join(LAST_REPORT_FILE);
+    serde_json::from_str(&std::fs::read_to_string(path).ok()?).ok()
 }
 
-fn exec_test(
-    root: &Path,
-    container_name: &str,
-    common_args: &[&str],
-    results: &Path,
-) -> Result<UnitTest, TestError> {
-    let _ = std::process::Command::new("docker")
-        .args(common_args)
-        .arg("exec")
-        .arg(container_name)
-        .arg("make")
-        .arg("-C")
-        .arg("src")
-        .arg("run_tests")
-        .current_dir(root)
-        .output()
-        .unwrap();
+/// The git tree of the exercise tested by the last local run, see [`save_results`]
+pub fn last_tree(root: &Path) -> Option<String> {
+    let path = root
+        .join(STATE_DIR)
+        .join(LAST_RESULTS_DIR)
+        .join(LAST_TREE_FILE);
+    std::fs::read_to_string(path).ok()
+}
 
-    let asan = Asan::try_from_file(&results.with_file_name(ASAN_FILE));
-    let gunit = UnitTest::try_from_file(results);
-    match (gunit, asan) {
-        (Ok(mut gunit), Ok(asan)) => {
-            gunit.add_suite(asan);
-            Ok(gunit)
-        },
-        | (Ok(mut gunit), Err(asan))
-        | (Err(TestError::TestFailed(mut gunit)), Ok(asan))
-        | (Err(TestError::TestFailed(mut gunit)), Err(asan)) => {
-            gunit.add_suite(asan);
-            Err(TestError::TestFailed(gunit))
-        }
-        (_, Err(err)) => UnitTest::try_from(err),
-        (Err(err), _) => Err(err),
+/// Show the sanitizer report of the results, when it found errors
+pub fn print_memory_report(results: &Path, files: &ResultFiles, paths: &PathMapper) {
+    if logger::quiet() {
+        return;
+    }
+    if let Some(report) = Asan::from_file(&results.join(&files.memory)).report(paths) {
+        print!("{}", report.bright_black());
     }
 }
@@ -0,0 +1,155 @@
+use crate::dojo::DojoResult;
+use crate::paths::PathMapper;
+use compose_spec::service::volumes::mount::{Bind, Mount};
+use compose_spec::service::volumes::{AbsolutePath, HostPath, ShortVolume};
+use compose_spec::service::Command;
+use compose_spec::{Compose, Identifier, ListOrMap, Service, ShortOrLong};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+pub const COMPOSE_FILE: &str = "docker-compose.yml";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum ComposeError {
+    #[error("unable to read docker-compose.yml: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid docker-compose.yml: {0}")]
+    Yaml(#[from] serde_yaml::Error),
+
+    #[error("service '{0}' not found in docker-compose.yml")]
+    ServiceNotFound(Identifier),
+
+    #[error("no result volume declared in dojo_assignment.json")]
+    MissingVolume,
+
+    #[error("volume '{0}' not found in docker-compose.yml")]
+    VolumeNotFound(Identifier),
+
+    #[error("volume '{volume}' is not mounted by service '{service}' in docker-compose.yml")]
+    VolumeNotMounted {
+        volume: Identifier,
+        service: Identifier,
+    },
+
+    #[error("invalid results directory: {0}")]
+    ResultsDirectory(PathBuf),
+}
+
+/// Load the `docker-compose.yml` of an exercise
+pub fn load(path: &Path) -> Result<Compose, ComposeError> {
+    let file = std::fs::read_to_string(path)?;
+    serde_yaml::from_str(&file).map_err(Into::into)
+}
+
+/// The docker compose setup of an exercise, validated against its assignment
+#[derive(Debug, Clone)]
+pub struct Environment {
+    /// Directory of the compose file, where its relative paths are resolved from
+    pub root: PathBuf,
+
+    pub compose: Compose,
+
+    /// The service the tests are run in
+    pub service: Identifier,
+
+    /// The volume the service writes its results to
+    pub volume: Identifier,
+}
+
+impl Environment {
+    pub fn load(path: &Path, result: &DojoResult) -> Result<Self, ComposeError> {
+        let compose = load(path)?;
+        let service = result.container.clone();
+        let volume = result.volume.clone().ok_or(ComposeError::MissingVolume)?;
+
+        let definition = compose
+            .services
+            .get(&service)
+            .ok_or_else(|| ComposeError::ServiceNotFound(service.clone()))?;
+
+        if !compose.volumes.contains_key(&volume) {
+            return Err(ComposeError::VolumeNotFound(volume));
+        }
+
+        let mounted = definition
+            .volumes
+            .iter()
+            .any(|mount| result_mount(mount, &volume).is_some());
+        if !mounted {
+            return Err(ComposeError::VolumeNotMounted { volume, service });
+        }
+
+        Ok(Self {
+            root: path.parent().unwrap_or(Path::new("")).to_path_buf(),
+            compose,
+            service,
+            volume,
+        })
+    }
+
+    pub fn path_mapper(&self) -> PathMapper {
+        PathMapper::new(&self.root).with_compose(&self.compose, &self.service)
+    }
+
+    /// Where the result volume is mounted inside the container
+    pub fn results_target(&self) -> Option<PathBuf> {
+        self.compose.services[&self.service]
+            .volumes
+            .iter()
+            .find_map(|mount| result_mount(mount, &self.volume))
+            .map(|target| target.as_path().to_path_buf())
+    }
+
+    /// Write the compose file overriding the service so that it stays idle, waiting for
+    /// commands to be executed, and writes its results to `results` on the host
+    pub fn write_override(&self, results: &Path) -> Result<PathBuf, ComposeError> {
+        let source = HostPath::new(results)
+            .map_err(|_| ComposeError::ResultsDirectory(results.to_path_buf()))?;
+
+        // Mounts are merged by their target, replacing the result volume by the bind mount
+        let volumes = self.compose.services[&self.service]
+            .volumes
+            .iter()
+            .map(|mount| match result_mount(mount, &self.volume) {
+                Some(target) => {
+                    ShortOrLong::Long(Mount::Bind(Bind::new(source.clone(), target.into())))
+                }
+                None => mount.clone(),
+            })
+            .collect();
+
+        let service = Service {
+            entrypoint: Some(Command::List(vec![
+                "sleep".to_string(),
+                "infinity".to_string(),
+            ])),
+            environment: ListOrMap::List(["IN_DOCKER=true".to_string()].into_iter().collect()),
+            volumes,
+            ..Default::default()
+        };
+
+        let compose = Compose {
+            services: [(self.service.clone(), service)].into_iter().collect(),
+            ..Default::default()
+        };
+
+        let path = results.join("docker-compose.yml");
+        std::fs::write(&path, serde_yaml::to_string(&compose)?)?;
+        Ok(path)
+    }
+}
+
+/// The target of `mount` if it mounts the named `volume`
+fn result_mount(
+    mount: &ShortOrLong<ShortVolume, Mount>,
+    volume: &Identifier,
+) -> Option<AbsolutePath> {
+    match Mount::from(mount.clone()) {
+        Mount::Volume(mounted) if mounted.source.as_ref() == Some(volume) => {
+            Some(mounted.common.target)
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,444 @@
+use crate::gitlab::DEFAULT_HOST;
+use clap::ValueEnum;
+use neodojo::pipeline::ResultFiles;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+use toml::{Table, Value};
+
+/// Environment variable overriding the directory of the per-user configuration
+pub const CONFIG_DIR_ENV: &str = "NEODOJO_CONFIG_DIR";
+
+/// Prefix of the environment variables overriding the settings, e.g. `NEODOJO_TIMEOUTS_TEST`
+/// for `timeouts.test`
+const ENV_PREFIX: &str = "NEODOJO_";
+
+const CONFIG_FILE: &str = "config.toml";
+
+/// Configuration of a workspace, at its root
+pub const WORKSPACE_CONFIG_FILE: &str = ".neodojo.toml";
+
+/// Every setting, as written in the configuration files
+pub const KEYS: [&str; 10] = [
+    "runtime",
+    "format",
+    "color",
+    "reminders",
+    "timeouts.build",
+    "timeouts.test",
+    "results.tests",
+    "results.memory",
+    "gitlab.host",
+    "gitlab.group",
+];
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("unable to locate the configuration directory, set ${CONFIG_DIR_ENV}")]
+    NoConfigDir,
+
+    #[error("unable to access {0}: {1}")]
+    Io(PathBuf, std::io::Error),
+
+    #[error("invalid configuration in {0}: {1}")]
+    Invalid(String, String),
+
+    #[error("unknown setting '{0}'")]
+    UnknownKey(String),
+
+    #[error("no workspace found to write its {WORKSPACE_CONFIG_FILE} to")]
+    NoWorkspace,
+}
+
+/// Where a setting comes from, each one overriding the previous ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Default,
+    User,
+    Workspace,
+    Environment,
+}
+
+impl std::fmt::Display for Source {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Source::Default => write!(f, "default"),
+            Source::User => write!(f, "user"),
+            Source::Workspace => write!(f, "workspace"),
+            Source::Environment => write!(f, "environment"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human readable results
+    Pretty,
+
+    /// The whole report of the run as JSON
+    Json,
+
+    /// The results as JUnit XML, for CI systems
+    Junit,
+
+    /// The results as TAP version 13, for TAP harnesses like `prove`
+    Tap,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorMode {
+    /// Color when writing to a terminal
+    Auto,
+    Always,
+    Never,
+}
+
+/// Settings of neodojo, see [`Layers`] for where they are read from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Container runtime running the exercise, `docker` or a compatible one like `podman`
+    pub runtime: String,
+
+    pub format: OutputFormat,
+
+    pub color: ColorMode,
+
+    /// Remind to commit and push after the tests were run
+    pub reminders: bool,
+
+    pub timeouts: Timeouts,
+
+    pub results: ResultFiles,
+
+    pub gitlab: GitLabConfig,
+}
+
+/// Time limits, in seconds
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Timeouts {
+    pub build: Option<u64>,
+    pub test: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GitLabConfig {
+    pub host: String,
+
+    /// Group the assignments are published in
+    pub group: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            runtime: "docker".to_string(),
+            format: OutputFormat::Pretty,
+            color: ColorMode::Auto,
+            reminders: true,
+            timeouts: Timeouts::default(),
+            results: ResultFiles::default(),
+            gitlab: GitLabConfig::default(),
+        }
+    }
+}
+
+impl Default for GitLabConfig {
+    fn default() -> Self {
+        Self {
+            host: DEFAULT_HOST.to_string(),
+            group: None,
+        }
+    }
+}
+
+impl Config {
+    /// Load the configuration of `workspace`, or of the user only outside of a workspace
+    pub fn load(workspace: Option<&Path>) -> Result<Self, ConfigError> {
+        Layers::load(workspace)?.resolve()
+    }
+
+    /// Apply the color setting to everything printed afterwards
+    pub fn apply_color(&self) {
+        match self.color {
+            ColorMode::Auto => {}
+            ColorMode::Always => colored::control::set_override(true),
+            ColorMode::Never => colored::control::set_override(false),
+        }
+    }
+}
+
+impl Timeouts {
+    pub fn build(&self) -> Option<Duration> {
+        self.build.map(Duration::from_secs)
+    }
+
+    pub fn test(&self) -> Option<Duration> {
+        self.test.map(Duration::from_secs)
+    }
+}
+
+/// The configuration sources: built-in defaults, then `~/.config/neodojo/config.toml`, then the
+/// `.neodojo.toml` of the workspace, then the `NEODOJO_*` environment variables. Command line
+/// flags are applied last, by the commands themselves.
+#[derive(Debug, Clone)]
+pub struct Layers {
+    layers: Vec<(Source, Table)>,
+}
+
+impl Layers {
+    pub fn load(workspace: Option<&Path>) -> Result<Self, ConfigError> {
+        let defaults = Table::try_from(Config::default())
+            .map_err(|err| ConfigError::Invalid("defaults".to_string(), err.to_string()))?;
+        let mut layers = vec![(Source::Default, defaults)];
+
+        if let Some(path) = Self::path(Source::User, workspace) {
+            layers.push((Source::User, read(&path)?));
+        }
+        if let Some(path) = Self::path(Source::Workspace, workspace) {
+            layers.push((Source::Workspace, read(&path)?));
+        }
+
+        let mut environment = Table::new();
+        for key in KEYS {
+            let var = format!("{ENV_PREFIX}{}", key.replace('.', "_").to_uppercase());
+            if let Ok(value) = std::env::var(&var) {
+                insert(&mut environment, key, parse_value(&value));
+            }
+        }
+        validate(&environment, &format!("${ENV_PREFIX}*"))?;
+        layers.push((Source::Environment, environment));
+
+        Ok(Self { layers })
+    }
+
+    /// The file of a layer
+    pub fn path(source: Source, workspace: Option<&Path>) -> Option<PathBuf> {
+        match source {
+            Source::User => config_dir().map(|dir| dir.join(CONFIG_FILE)),
+            Source::Workspace => workspace.map(|root| root.join(WORKSPACE_CONFIG_FILE)),
+            Source::Default | Source::Environment => None,
+        }
+    }
+
+    pub fn resolve(&self) -> Result<Config, ConfigError> {
+        let mut merged = Table::new();
+        for (_, layer) in &self.layers {
+            merge(&mut merged, layer);
+        }
+        Config::deserialize(merged).map_err(|err| {
+            ConfigError::Invalid("configuration".to_string(), err.message().to_string())
+        })
+    }
+
+    /// The value of a setting along with the layer it comes from, `None` when unset
+    pub fn get(&self, key: &str) -> Result<Option<(&Value, Source)>, ConfigError> {
+        if !KEYS.contains(&key) {
+            return Err(ConfigError::UnknownKey(key.to_string()));
+        }
+
+        Ok(self
+            .layers
+            .iter()
+            .rev()
+            .find_map(|(source, layer)| Some((lookup(layer, key)?, *source))))
+    }
+}
+
+/// Set a setting in the file of the user or of the workspace
+pub fn set(path: &Path, key: &str, value: &str) -> Result<(), ConfigError> {
+    if !KEYS.contains(&key) {
+        return Err(ConfigError::UnknownKey(key.to_string()));
+    }
+
+    let mut table = read(path)?;
+    insert(&mut table, key, parse_value(value));
+    validate(&table, &path.display().to_string())?;
+
+    let content = toml::to_string_pretty(&table)
+        .map_err(|err| ConfigError::Invalid(path.display().to_string(), err.to_string()))?;
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir).map_err(|err| ConfigError::Io(dir.to_path_buf(), err))?;
+    }
+    std::fs::write(path, content).map_err(|err| ConfigError::Io(path.to_path_buf(), err))
+}
+
+/// Directory of the per-user configuration, `~/.config/neodojo` on Linux
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os(CONFIG_DIR_ENV)
+        .map(PathBuf::from)
+        .or_else(|| dirs::config_dir().map(|dir| dir.join("neodojo")))
+}
+
+/// Read a configuration file, missing files being empty
+fn read(path: &Path) -> Result<Table, ConfigError> {
+    let file = match std::fs::read_to_string(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Table::new()),
+        Err(err) => return Err(ConfigError::Io(path.to_path_buf(), err)),
+    };
+
+    let table = file.parse::<Table>().map_err(|err| {
+        ConfigError::Invalid(path.display().to_string(), err.message().to_string())
+    })?;
+    validate(&table, &path.display().to_string())?;
+    Ok(table)
+}
+
+/// Check a layer on its own, so that errors point to where they are
+fn validate(table: &Table, origin: &str) -> Result<(), ConfigError> {
+    Config::deserialize(table.clone())
+        .map(|_| ())
+        .map_err(|err| ConfigError::Invalid(origin.to_string(), err.message().to_string()))
+}
+
+/// A value given on the command line or in the environment, as TOML if it is valid TOML and
+/// as a string otherwise
+fn parse_value(value: &str) -> Value {
+    format!("value = {value}")
+        .parse::<Table>()
+        .ok()
+        .and_then(|mut table| table.remove("value"))
+        .unwrap_or_else(|| Value::String(value.to_string()))
+}
+
+fn lookup<'a>(table: &'a Table, key: &str) -> Option<&'a Value> {
+    match key.split_once('.') {
+        Some((section, key)) => lookup(table.get(section)?.as_table()?, key),
+        None => table.get(key),
+    }
+}
+
+fn insert(table: &mut Table, key: &str, value: Value) {
+    match key.split_once('.') {
+        Some((section, key)) => {
+            let section = table
+                .entry(section)
+                .or_insert_with(|| Value::Table(Table::new()));
+            if !section.is_table() {
+                *section = Value::Table(Table::new());
+            }
+            if let Value::Table(section) = section {
+                insert(section, key, value);
+            }
+        }
+        None => {
+            table.insert(key.to_string(), value);
+        }
+    }
+}
+
+/// Merge `layer` into `merged`, its values overriding the previous ones
+fn merge(merged: &mut Table, layer: &Table) {
+    for (key, value) in layer {
+        match (merged.get_mut(key), value) {
+            (Some(Value::Table(merged)), Value::Table(layer)) => merge(merged, layer),
+            _ => {
+                merged.insert(key.clone(), value.clone());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// The configuration directory and the `NEODOJO_*` variables are set for the whole process,
+    /// the tests changing them hold this lock
+    pub(crate) static ENVIRONMENT: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn layers_override_each_other() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let (user, workspace) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        std::env::set_var(CONFIG_DIR_ENV, user.path());
+        std::fs::write(
+            user.path().join(CONFIG_FILE),
+            "runtime = \"podman\"\n\n[timeouts]\nbuild = 60\ntest = 10\n",
+        )
+        .unwrap();
+        std::fs::write(
+            workspace.path().join(WORKSPACE_CONFIG_FILE),
+            "[timeouts]\ntest = 30\n",
+        )
+        .unwrap();
+        std::env::set_var("NEODOJO_FORMAT", "json");
+
+        let layers = Layers::load(Some(workspace.path()));
+        std::env::remove_var("NEODOJO_FORMAT");
+        let layers = layers.unwrap();
+        let config = layers.resolve().unwrap();
+        assert_eq!(config.runtime, "podman");
+        assert_eq!(config.format, OutputFormat::Json);
+        assert_eq!(config.timeouts.build, Some(60));
+        assert_eq!(config.timeouts.test, Some(30));
+        assert!(config.reminders);
+
+        let source = |key| layers.get(key).unwrap().map(|(_, source)| source);
+        assert_eq!(source("runtime"), Some(Source::User));
+        assert_eq!(source("timeouts.test"), Some(Source::Workspace));
+        assert_eq!(source("format"), Some(Source::Environment));
+        assert_eq!(source("color"), Some(Source::Default));
+        assert_eq!(source("gitlab.group"), None);
+        assert!(matches!(
+            layers.get("timeout"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_layers_are_located() {
+        let _lock = ENVIRONMENT.lock().unwrap_or_else(|err| err.into_inner());
+        let (user, workspace) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        std::env::set_var(CONFIG_DIR_ENV, user.path());
+        let path = workspace.path().join(WORKSPACE_CONFIG_FILE);
+        std::fs::write(&path, "[timeouts]\ntset = 30\n").unwrap();
+
+        let Err(ConfigError::Invalid(origin, _)) = Layers::load(Some(workspace.path())) else {
+            panic!("the unknown field is accepted");
+        };
+        assert_eq!(origin, path.display().to_string());
+
+        std::fs::remove_file(&path).unwrap();
+        std::env::set_var("NEODOJO_REMINDERS", "sometimes");
+        let layers = Layers::load(Some(workspace.path()));
+        std::env::remove_var("NEODOJO_REMINDERS");
+        let Err(ConfigError::Invalid(origin, _)) = layers else {
+            panic!("the invalid value is accepted");
+        };
+        assert_eq!(origin, "$NEODOJO_*");
+    }
+
+    #[test]
+    fn set_keeps_the_other_settings() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("neodojo").join(CONFIG_FILE);
+        set(&path, "gitlab.group", "dojo").unwrap();
+        set(&path, "timeouts.test", "30").unwrap();
+        set(&path, "reminders", "false").unwrap();
+
+        let table = read(&path).unwrap();
+        assert_eq!(
+            lookup(&table, "gitlab.group"),
+            Some(&Value::String("dojo".to_string()))
+        );
+        assert_eq!(lookup(&table, "timeouts.test"), Some(&Value::Integer(30)));
+        assert_eq!(lookup(&table, "reminders"), Some(&Value::Boolean(false)));
+
+        assert!(matches!(
+            set(&path, "timeouts.test", "soon"),
+            Err(ConfigError::Invalid(_, _))
+        ));
+        assert!(matches!(
+            set(&path, "gitlab.token", "secret"),
+            Err(ConfigError::UnknownKey(_))
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+use crate::config::{self, CONFIG_DIR_ENV};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::PathBuf;
+use thiserror::Error;
+
+const CREDENTIALS_FILE: &str = "credentials.json";
+
+#[derive(Debug, Error)]
+pub enum CredentialsError {
+    #[error("unable to locate the configuration directory, set ${CONFIG_DIR_ENV}")]
+    NoConfigDir,
+
+    #[error("unable to access the credentials: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("invalid credentials file: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+/// How a token was obtained, which decides how it is revoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenKind {
+    PersonalAccessToken,
+    OAuth,
+}
+
+/// The login to a GitLab instance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Credential {
+    pub token: String,
+    pub kind: TokenKind,
+
+    /// The OAuth application the token was issued to
+    pub client_id: Option<String>,
+
+    pub username: String,
+}
+
+/// The logins of the user, by GitLab instance
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Credentials {
+    pub hosts: BTreeMap<String, Credential>,
+}
+
+fn config_dir() -> Result<PathBuf, CredentialsError> {
+    config::config_dir().ok_or(CredentialsError::NoConfigDir)
+}
+
+impl Credentials {
+    pub fn load() -> Result<Self, CredentialsError> {
+        let path = config_dir()?.join(CREDENTIALS_FILE);
+        match std::fs::read_to_string(path) {
+            Ok(file) => Ok(serde_json::from_str(&file)?),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Write the credentials, readable by the current user only
+    pub fn save(&self) -> Result<(), CredentialsError> {
+        let dir = config_dir()?;
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(CREDENTIALS_FILE);
+
+        let mut options = std::fs::OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+            options.mode(0o600);
+            // The mode only applies to new files, older ones are restricted too
+            if path.exists() {
+                std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+            }
+        }
+
+        let mut file = options.open(&path)?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    pub fn get(&self, host: &str) -> Option<&Credential> {
+        self.hosts.get(host.trim_end_matches('/'))
+    }
+
+    pub fn insert(&mut self, host: &str, credential: Credential) {
+        self.hosts
+            .insert(host.trim_end_matches('/').to_string(), credential);
+    }
+
+    pub fn remove(&mut self, host: &str) -> Option<Credential> {
+        self.hosts.remove(host.trim_end_matches('/'))
+    }
+}
@@ -1,23 +1,60 @@
+use crate::framework::TestFramework;
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use codespan_reporting::files::{Files, SimpleFiles};
+use codespan_reporting::term::termcolor::{StandardStream, WriteColor};
 use compose_spec::Identifier;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::path::Path;
+use std::time::Duration;
 use thiserror::Error;
 
+pub const ASSIGNMENT_FILE: &str = "dojo_assignment.json";
+
+/// Migrations of the legacy schemas, the n-th one migrating from version n to version n + 1
+const MIGRATIONS: [fn(&mut Map<String, Value>); 2] = [migrate_unversioned, migrate_v1];
+
+/// Fields added after the first version of the schema, with the version adding them
+const ADDED_FIELDS: &[(&str, SchemaVersion)] = &[
+    ("testFramework", SchemaVersion::V2),
+    ("slowTestThreshold", SchemaVersion::V2),
+];
+
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum DojoAssignmentError {
     #[error("I/O error")]
     Io(#[from] std::io::Error),
 
-    #[error("JSON error")]
-    Serde(#[from] serde_json::Error),
+    #[error("{0}")]
+    Invalid(Box<SchemaError>),
+}
+
+/// A `dojo_assignment.json` that does not match the schema
+#[derive(Debug, Error)]
+#[error("{}{message}", if path.is_empty() { String::new() } else { format!("{path}: ") })]
+pub struct SchemaError {
+    /// Name of the assignment file
+    pub file: String,
+
+    /// Content of the assignment file
+    pub content: String,
+
+    /// Path to the invalid value within the JSON document (e.g. `result.container`)
+    pub path: String,
+
+    pub message: String,
+
+    /// Line and column of the invalid value, when known
+    pub position: Option<(usize, usize)>,
 }
 
 /// Dojo assignment configuration
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DojoAssignment {
-    /// I have absolutely NO idea what this is for
-    pub dojo_assignment_version: u32,
+    /// The version of the schema of this file
+    pub dojo_assignment_version: SchemaVersion,
 
     /// Neither do I know what this is for
     pub version: u32,
@@ -28,11 +65,59 @@ pub struct DojoAssignment {
     /// The reference to the result (both the container and the volume)
     /// This might be the ONLY shred of valuable information in this file...
     pub result: DojoResult,
+
+    /// The framework writing the test results, detected from them when missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test_framework: Option<TestFramework>,
+
+    /// Number of seconds above which a test is reported as slow, for the exercises graded on
+    /// speed
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "crate::report::optional_seconds"
+    )]
+    pub slow_test_threshold: Option<Duration>,
+}
+
+/// Versions of the `dojo_assignment.json` schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "u32", into = "u32")]
+pub enum SchemaVersion {
+    V1 = 1,
+
+    /// Adds `testFramework` and `slowTestThreshold`
+    V2 = 2,
+}
+
+impl SchemaVersion {
+    pub const LATEST: SchemaVersion = SchemaVersion::V2;
+}
+
+impl TryFrom<u32> for SchemaVersion {
+    type Error = String;
+
+    fn try_from(version: u32) -> Result<Self, Self::Error> {
+        match version {
+            1 => Ok(Self::V1),
+            2 => Ok(Self::V2),
+            _ => Err(format!(
+                "unsupported schema version {version}, the latest supported by neodojo is {}",
+                u32::from(Self::LATEST)
+            )),
+        }
+    }
+}
+
+impl From<SchemaVersion> for u32 {
+    fn from(version: SchemaVersion) -> Self {
+        version as u32
+    }
 }
 
 /// Immutable file descriptor
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DojoImmutableFileDescriptor {
     /// A description of the file, again, for reasons
     pub description: Option<String>,
@@ -46,7 +131,7 @@ pub struct DojoImmutableFileDescriptor {
 
 /// Result configuration for a Dojo assignment
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 pub struct DojoResult {
     /// The container where the result is generated (?)
     pub container: Identifier,
@@ -56,8 +141,385 @@ pub struct DojoResult {
 }
 
 impl DojoAssignment {
+    /// Load an assignment, migrating it from a legacy schema if needed
     pub fn try_from_file(path: &Path) -> Result<Self, DojoAssignmentError> {
-        let file = std::fs::read_to_string(path)?;
-        serde_json::from_str(&file).map_err(Into::into)
+        let source = std::fs::read_to_string(path)?;
+        let invalid = |json_path: String, error: &serde_json::Error| {
+            let message = error.to_string();
+            let message = message
+                .rsplit_once(" at line ")
+                .map_or(message.as_str(), |(message, _)| message);
+
+            DojoAssignmentError::Invalid(Box::new(SchemaError {
+                file: path.display().to_string(),
+                content: source.clone(),
+                path: json_path.trim_start_matches('.').to_string(),
+                message: message.to_string(),
+                position: (error.line() > 0).then(|| (error.line(), error.column())),
+            }))
+        };
+
+        let mut value: Value =
+            serde_json::from_str(&source).map_err(|err| invalid(String::new(), &err))?;
+
+        // Files written before the schema was versioned have no version at all
+        let version = match value.get("dojoAssignmentVersion") {
+            None => Some(0),
+            Some(version) => version.as_u64(),
+        };
+        let latest = u32::from(SchemaVersion::LATEST);
+
+        match (version, value.as_object_mut()) {
+            (Some(version), Some(assignment)) if version < latest as u64 => {
+                // Older neodojo versions reject the fields they do not know, so does this one
+                let added = ADDED_FIELDS.iter().find(|(field, since)| {
+                    version < u32::from(*since) as u64 && assignment.contains_key(*field)
+                });
+                if let Some((field, since)) = added {
+                    return Err(DojoAssignmentError::Invalid(Box::new(SchemaError {
+                        file: path.display().to_string(),
+                        content: source.clone(),
+                        path: field.to_string(),
+                        message: format!(
+                            "unknown field `{field}` in version {version} of the schema, set \
+                             `dojoAssignmentVersion` to {} to use it",
+                            u32::from(*since)
+                        ),
+                        position: locate(&source, field, true),
+                    })));
+                }
+
+                for migration in &MIGRATIONS[version as usize..] {
+                    migration(assignment);
+                }
+                assignment.insert("dojoAssignmentVersion".to_string(), latest.into());
+
+                // The errors of the migrated document are mapped back to the source
+                serde_path_to_error::deserialize(value).map_err(|err| {
+                    let path = err.path().to_string();
+                    let mut error = invalid(path.clone(), err.inner());
+                    if let DojoAssignmentError::Invalid(error) = &mut error {
+                        let key = error.message.starts_with("unknown field");
+                        error.position = locate(&source, &error.path, key);
+                    }
+                    error
+                })
+            }
+            _ => {
+                let mut deserializer = serde_json::Deserializer::from_str(&source);
+                serde_path_to_error::deserialize(&mut deserializer)
+                    .map_err(|err| invalid(err.path().to_string(), err.inner()))
+            }
+        }
+    }
+
+    /// The immutable files and directories, relative to the workspace root
+    pub fn immutable_paths(&self) -> Vec<&str> {
+        self.immutable
+            .iter()
+            .map(|file| file.path.trim_start_matches('/'))
+            .collect()
+    }
+}
+
+/// Assignments predating the versioned schema could omit the version and the immutable files
+fn migrate_unversioned(assignment: &mut Map<String, Value>) {
+    assignment.entry("version").or_insert(1.into());
+    assignment
+        .entry("immutable")
+        .or_insert(Value::Array(Vec::new()));
+}
+
+/// Version 2 only adds optional fields
+fn migrate_v1(_assignment: &mut Map<String, Value>) {}
+
+impl DojoAssignmentError {
+    pub fn pretty_print(&self) {
+        if let DojoAssignmentError::Invalid(error) = self {
+            error.pretty_print();
+        }
+    }
+}
+
+impl SchemaError {
+    /// Show the invalid value within the file, if its position is known
+    pub fn pretty_print(&self) {
+        let writer = StandardStream::stdout(crate::color_choice());
+        self.emit(&mut writer.lock());
+    }
+
+    fn emit(&self, writer: &mut dyn WriteColor) {
+        let Some((line, column)) = self.position else {
+            return;
+        };
+
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(self.file.clone(), self.content.clone());
+        let Ok(range) = files.line_range(file_id, line.saturating_sub(1)) else {
+            return;
+        };
+
+        // serde_json reports the position of the end of the invalid value, in bytes
+        let mut end = (range.start + column).min(range.end);
+        while !self.content.is_char_boundary(end) {
+            end -= 1;
+        }
+        let value = self.content[range.start..end].trim_end();
+        let start = range.start + value.rfind([' ', ':', ',', '{', '[']).map_or(0, |i| i + 1);
+
+        let config = codespan_reporting::term::Config::default();
+        let diagnostic = Diagnostic::error()
+            .with_message(self.to_string())
+            .with_labels(vec![
+                Label::primary(file_id, start..end.max(start)).with_message(&self.message)
+            ]);
+        codespan_reporting::term::emit(writer, &config, &files, &diagnostic).unwrap();
+    }
+}
+
+/// Where the value at `path`, e.g. `immutable[0].path`, ends in the JSON `source`, as the line
+/// and column serde_json reports. With `key`, where the key of the value ends instead.
+fn locate(source: &str, path: &str, key: bool) -> Option<(usize, usize)> {
+    let mut segments = Vec::new();
+    for part in path.split('.').filter(|part| !part.is_empty()) {
+        let (name, indices) = part.split_once('[').unwrap_or((part, ""));
+        if !name.is_empty() {
+            segments.push(Err(name));
+        }
+        for index in indices.split('[') {
+            if let Some(index) = index.strip_suffix(']') {
+                segments.push(Ok(index.parse::<usize>().ok()?));
+            }
+        }
+    }
+
+    let mut cursor = Cursor { source, offset: 0 };
+    for (i, segment) in segments.iter().enumerate() {
+        match *segment {
+            Err(name) => {
+                cursor.eat(b'{')?;
+                loop {
+                    let found = cursor.string()? == name;
+                    if found && key && i == segments.len() - 1 {
+                        return Some(cursor.position());
+                    }
+                    cursor.eat(b':')?;
+                    if found {
+                        break;
+                    }
+                    cursor.skip_value()?;
+                    cursor.eat(b',')?;
+                }
+            }
+            Ok(index) => {
+                cursor.eat(b'[')?;
+                for _ in 0..index {
+                    cursor.skip_value()?;
+                    cursor.eat(b',')?;
+                }
+            }
+        }
+    }
+    cursor.skip_value()?;
+    Some(cursor.position())
+}
+
+/// Reads through a JSON document, only to find where its values are
+struct Cursor<'a> {
+    source: &'a str,
+    offset: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn peek(&mut self) -> Option<u8> {
+        let rest = &self.source.as_bytes()[self.offset..];
+        let whitespace = rest.iter().take_while(|b| b.is_ascii_whitespace()).count();
+        self.offset += whitespace;
+        rest.get(whitespace).copied()
+    }
+
+    fn eat(&mut self, byte: u8) -> Option<()> {
+        (self.peek()? == byte).then(|| self.offset += 1)
+    }
+
+    /// The raw content of a string, escapes included
+    fn string(&mut self) -> Option<&'a str> {
+        self.eat(b'"')?;
+        let start = self.offset;
+        loop {
+            match self.source.as_bytes().get(self.offset)? {
+                b'\\' => self.offset += 2,
+                b'"' => break,
+                _ => self.offset += 1,
+            }
+        }
+        self.offset += 1;
+        self.source.get(start..self.offset - 1)
+    }
+
+    fn skip_value(&mut self) -> Option<()> {
+        match self.peek()? {
+            b'"' => {
+                self.string()?;
+            }
+            b'{' | b'[' => {
+                let mut depth = 0;
+                loop {
+                    match self.peek()? {
+                        b'"' => {
+                            self.string()?;
+                            continue;
+                        }
+                        b'{' | b'[' => depth += 1,
+                        b'}' | b']' => depth -= 1,
+                        _ => {}
+                    }
+                    self.offset += 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            _ => {
+                let rest = &self.source.as_bytes()[self.offset..];
+                self.offset += rest
+                    .iter()
+                    .take_while(|b| !b.is_ascii_whitespace() && !b",}]".contains(b))
+                    .count();
+            }
+        }
+        Some(())
+    }
+
+    /// The line and the column of the offset, both starting at 1 as with serde_json
+    fn position(&self) -> (usize, usize) {
+        let before = &self.source[..self.offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        (before.matches('\n').count() + 1, self.offset - line_start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codespan_reporting::term::termcolor::NoColor;
+
+    const ASSIGNMENT: &str = r#"{
+  "version": 1,
+  "immutable": [
+    { "description": "Suite de tests é", "path": "tests/", "isDirectory": true },
+    { "path": "src/Makefile", "bogus": 1 }
+  ],
+  "result": { "container": 12 }
+}"#;
+
+    #[test]
+    fn locate_finds_values() {
+        assert_eq!(locate(ASSIGNMENT, "version", false), Some((2, 14)));
+        assert_eq!(
+            locate(ASSIGNMENT, "immutable[1].bogus", true),
+            Some((5, 37))
+        );
+        assert_eq!(
+            locate(ASSIGNMENT, "immutable[1].bogus", false),
+            Some((5, 40))
+        );
+        assert_eq!(locate(ASSIGNMENT, "result.container", false), Some((7, 29)));
+        assert_eq!(locate(ASSIGNMENT, "result.volume", false), None);
+        assert_eq!(locate(ASSIGNMENT, "", false), Some((8, 1)));
+    }
+
+    /// Write `content` as an assignment and load it
+    fn load(content: &str) -> Result<DojoAssignment, DojoAssignmentError> {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(ASSIGNMENT_FILE);
+        std::fs::write(&path, content).unwrap();
+        DojoAssignment::try_from_file(&path)
+    }
+
+    #[test]
+    fn migrated_errors_keep_their_position() {
+        let Err(DojoAssignmentError::Invalid(error)) = load(ASSIGNMENT) else {
+            panic!("the assignment is invalid");
+        };
+        assert_eq!(error.path, "immutable[1].bogus");
+        assert_eq!(error.position, Some((5, 37)));
+    }
+
+    #[test]
+    fn pretty_print_handles_multibyte_content() {
+        // A column within `é`, which takes two bytes
+        let error = SchemaError {
+            file: ASSIGNMENT_FILE.to_string(),
+            content: "{ \"version\": \"é\" }".to_string(),
+            path: "version".to_string(),
+            message: "invalid type".to_string(),
+            position: Some((1, 15)),
+        };
+        let mut writer = NoColor::new(Vec::new());
+        error.emit(&mut writer);
+
+        let rendered = String::from_utf8(writer.into_inner()).unwrap();
+        assert!(
+            rendered.contains("1 │ { \"version\": \"é\" }"),
+            "{rendered}"
+        );
+        assert!(
+            rendered.contains("│              ^ invalid type"),
+            "{rendered}"
+        );
+    }
+
+    #[test]
+    fn migrates_unversioned_assignments() {
+        let assignment = load(r#"{ "result": { "container": "exercise" } }"#).unwrap();
+        assert_eq!(assignment.dojo_assignment_version, SchemaVersion::LATEST);
+        assert!(assignment.immutable.is_empty());
+    }
+
+    #[test]
+    fn migrates_version_1() {
+        let assignment = load(
+            r#"{ "dojoAssignmentVersion": 1, "version": 1, "immutable": [],
+                 "result": { "container": "exercise" } }"#,
+        )
+        .unwrap();
+        assert_eq!(assignment.dojo_assignment_version, SchemaVersion::V2);
+        assert_eq!(assignment.test_framework, None);
+    }
+
+    #[test]
+    fn rejects_the_fields_of_later_versions() {
+        let content = r#"{
+  "dojoAssignmentVersion": 1,
+  "version": 1,
+  "immutable": [],
+  "result": { "container": "exercise" },
+  "testFramework": "tap"
+}"#;
+        let Err(DojoAssignmentError::Invalid(error)) = load(content) else {
+            panic!("`testFramework` needs version 2");
+        };
+        assert_eq!(error.path, "testFramework");
+        assert!(error.message.contains("set `dojoAssignmentVersion` to 2"));
+        assert_eq!(error.position, Some((6, 17)));
+
+        let assignment = load(&content.replace(": 1,\n  \"version", ": 2,\n  \"version")).unwrap();
+        assert_eq!(assignment.test_framework, Some(TestFramework::Tap));
+
+        let content = content.replace("\"testFramework\": \"tap\"", "\"slowTestThreshold\": 0.5");
+        let Err(DojoAssignmentError::Invalid(error)) = load(&content) else {
+            panic!("`slowTestThreshold` needs version 2");
+        };
+        assert_eq!(error.path, "slowTestThreshold");
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let Err(DojoAssignmentError::Invalid(error)) = load(r#"{ "dojoAssignmentVersion": 3 }"#)
+        else {
+            panic!("version 3 does not exist");
+        };
+        assert!(error.message.starts_with("unsupported schema version 3"));
     }
 }
@@ -0,0 +1,197 @@
+use super::{Element, TestFrameworkAdapter};
+use crate::gunit::TestError;
+use crate::report::{Failure, Status, TestOutcome};
+use std::time::Duration;
+
+/// Catch2, through the JUnit XML of its `junit` reporter. Any other JUnit report is read the same
+/// way.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Catch2;
+
+impl TestFrameworkAdapter for Catch2 {
+    fn detect(&self, content: &str) -> bool {
+        content.trim_start().starts_with('<')
+            && (content.contains("<testsuites") || content.contains("<testsuite"))
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        let document = Element::parse(content)?;
+        if document.descendants("testsuite").is_empty() {
+            return Err(TestError::Format("not a JUnit report".to_string()));
+        }
+
+        Ok(document
+            .descendants("testcase")
+            .into_iter()
+            .map(outcome)
+            .collect())
+    }
+}
+
+fn outcome(case: &Element) -> TestOutcome {
+    let failures = case
+        .children("failure")
+        .chain(case.children("error"))
+        .map(failure)
+        .collect::<Vec<_>>();
+    let skipped = case.child("skipped");
+    // An error is an unexpected exception, or a signal when Catch2 reports a fatal condition
+    let signal = case
+        .children("error")
+        .filter_map(|error| error.attribute("message"))
+        .find_map(signal);
+    let status = if signal.is_some() {
+        Status::Crash
+    } else if !failures.is_empty() {
+        Status::Fail
+    } else if skipped.is_some() {
+        Status::Skip
+    } else {
+        Status::Pass
+    };
+
+    // Catch2 names the classes after the executable, e.g. `tests.global`
+    let suite = case.attribute("classname").unwrap_or_default();
+    TestOutcome {
+        suite: suite.strip_suffix(".global").unwrap_or(suite).to_string(),
+        name: case.attribute("name").unwrap_or_default().to_string(),
+        status,
+        duration: case
+            .attribute("time")
+            .and_then(|time| Duration::try_from_secs_f64(time.parse().ok()?).ok()),
+        file: case.attribute("file").map(str::to_string),
+        line: case.attribute("line").and_then(|line| line.parse().ok()),
+        failures,
+        reason: skipped
+            .and_then(|skipped| skipped.attribute("message"))
+            .filter(|message| !message.is_empty())
+            .or(signal)
+            .map(str::to_string),
+    }
+}
+
+/// The signal of a fatal condition, e.g. `SIGSEGV - Segmentation violation signal`
+fn signal(message: &str) -> Option<&str> {
+    let (signal, _) = message.split_once(" - ")?;
+    (signal.starts_with("SIG") && signal.chars().all(|c| c.is_ascii_uppercase())).then_some(signal)
+}
+
+/// A failure, whose text ends with `at file:line` with Catch2
+fn failure(element: &Element) -> Failure {
+    let text = element.text.trim();
+    let message = match text {
+        "" => element.attribute("message").unwrap_or_default(),
+        text => text,
+    };
+
+    let location = message
+        .rsplit_once("\nat ")
+        .and_then(|(message, location)| {
+            let (file, line) = location.trim().rsplit_once(':')?;
+            Some((message, file, line.parse().ok()?))
+        });
+    match location {
+        Some((message, file, line)) => Failure {
+            message: message.trim().to_string(),
+            file: Some(file.to_string()),
+            line: Some(line),
+        },
+        None => Failure {
+            message: message.to_string(),
+            file: None,
+            line: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `tests --reporter junit` with Catch2 v3
+    const REPORT: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<testsuites>
+  <testsuite name="tests" errors="2" failures="1" skipped="1" tests="5" hostname="tbd" time="0.004" timestamp="2024-05-14T09:12:03Z">
+    <properties>
+      <property name="random-seed" value="2704291614"/>
+    </properties>
+    <testcase classname="tests.global" name="Factorials are computed" time="0.001" status="run">
+      <failure message="Factorial(0) == 1" type="REQUIRE">
+FAILED:
+  REQUIRE( Factorial(0) == 1 )
+with expansion:
+  0 == 1
+at /app/tests/tests.cpp:9
+      </failure>
+    </testcase>
+    <testcase classname="tests.global" name="Parses &amp; validates" time="0.000" status="run">
+      <error message="parse(&quot;x&quot;)" type="REQUIRE_NOTHROW">
+FAILED:
+  REQUIRE_NOTHROW( parse("x") )
+due to unexpected exception with message:
+  invalid input
+at /app/tests/tests.cpp:15
+      </error>
+    </testcase>
+    <testcase classname="tests.global" name="Dereferences" time="0.000" status="run">
+      <error message="SIGSEGV - Segmentation violation signal" type="FATAL_ERROR">
+FATAL_ERROR:
+  SIGSEGV - Segmentation violation signal
+at /app/tests/tests.cpp:20
+      </error>
+    </testcase>
+    <testcase classname="tests.global" name="Not yet" time="0.000" status="run">
+      <skipped message="TEST_CASE tagged with !mayfail"/>
+    </testcase>
+    <testcase classname="tests.global" name="Adds" time="0.002" status="run"/>
+  </testsuite>
+</testsuites>
+"#;
+
+    #[test]
+    fn detects_junit() {
+        assert!(Catch2.detect(REPORT));
+        assert!(!Catch2.detect("<CUNIT_TEST_RUN_REPORT>"));
+        assert!(Catch2.parse("<?xml version=\"1.0\"?><report/>").is_err());
+    }
+
+    #[test]
+    fn parses_outcomes() {
+        let outcomes = Catch2.parse(REPORT).unwrap();
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                Status::Fail,
+                Status::Fail,
+                Status::Crash,
+                Status::Skip,
+                Status::Pass
+            ]
+        );
+        assert_eq!(outcomes[0].suite, "tests");
+        assert_eq!(outcomes[1].name, "Parses & validates");
+        assert_eq!(outcomes[4].duration, Some(Duration::from_millis(2)));
+    }
+
+    #[test]
+    fn parses_failures() {
+        let outcomes = Catch2.parse(REPORT).unwrap();
+        let failure = &outcomes[0].failures[0];
+        assert_eq!(
+            failure.message,
+            "FAILED:\n  REQUIRE( Factorial(0) == 1 )\nwith expansion:\n  0 == 1"
+        );
+        assert_eq!(failure.file.as_deref(), Some("/app/tests/tests.cpp"));
+        assert_eq!(failure.line, Some(9));
+
+        // An exception is a failure, a signal a crash
+        assert!(outcomes[1].failures[0].message.contains("invalid input"));
+        assert_eq!(outcomes[1].reason, None);
+        assert_eq!(outcomes[2].reason.as_deref(), Some("SIGSEGV"));
+        assert_eq!(
+            outcomes[3].reason.as_deref(),
+            Some("TEST_CASE tagged with !mayfail")
+        );
+    }
+}
@@ -0,0 +1,187 @@
+use super::TestFrameworkAdapter;
+use crate::gunit::TestError;
+use crate::report::{Failure, Status, TestOutcome};
+use serde::Deserialize;
+
+/// Criterion, whose JSON report has no durations and no test locations
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Criterion;
+
+#[derive(Debug, Deserialize)]
+struct Report {
+    test_suites: Vec<Suite>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Suite {
+    name: String,
+    tests: Vec<Test>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Test {
+    name: String,
+    status: String,
+
+    /// Failed assertions, as `file:line: message`
+    #[serde(default)]
+    messages: Vec<String>,
+}
+
+impl TestFrameworkAdapter for Criterion {
+    fn detect(&self, content: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(content).is_ok_and(|json| {
+            json.get("test_suites").is_some()
+                || json
+                    .get("id")
+                    .and_then(|id| id.as_str())
+                    .is_some_and(|id| id.starts_with("Criterion"))
+        })
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        let report: Report = serde_json::from_str(content)?;
+        Ok(report
+            .test_suites
+            .into_iter()
+            .flat_map(|suite| {
+                suite.tests.into_iter().map(move |test| {
+                    let status = match test.status.as_str() {
+                        "PASSED" => Status::Pass,
+                        "SKIPPED" => Status::Skip,
+                        "ERRORED" => Status::Crash,
+                        _ => Status::Fail,
+                    };
+                    let failures = match status {
+                        Status::Skip => Vec::new(),
+                        _ => test
+                            .messages
+                            .iter()
+                            .map(|message| failure(message))
+                            .collect(),
+                    };
+                    TestOutcome {
+                        suite: suite.name.clone(),
+                        name: test.name,
+                        status,
+                        duration: None,
+                        file: None,
+                        line: None,
+                        failures,
+                        reason: match status {
+                            Status::Skip => test.messages.first().cloned(),
+                            _ => None,
+                        },
+                    }
+                })
+            })
+            .collect())
+    }
+}
+
+/// A message of Criterion, e.g. `tests.c:12: The expression add(1, 2) == 3 is false.`
+fn failure(message: &str) -> Failure {
+    let location = message.split_once(": ").and_then(|(location, message)| {
+        let (file, line) = location.rsplit_once(':')?;
+        Some((file, line.parse().ok()?, message))
+    });
+    match location {
+        Some((file, line, message)) => Failure {
+            message: message.to_string(),
+            file: Some(file.to_string()),
+            line: Some(line),
+        },
+        None => Failure {
+            message: message.to_string(),
+            file: None,
+            line: None,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `tests --json` with Criterion 2.4
+    const REPORT: &str = r#"{
+  "id": "Criterion v2.4.2",
+  "passed": 1,
+  "failed": 1,
+  "errored": 1,
+  "skipped": 1,
+  "test_suites": [
+    {
+      "name": "stack",
+      "passed": 1,
+      "failed": 1,
+      "errored": 1,
+      "skipped": 1,
+      "tests": [
+        {
+          "name": "push",
+          "assertions": 1,
+          "status": "PASSED"
+        },
+        {
+          "name": "pop",
+          "assertions": 2,
+          "status": "FAILED",
+          "messages": [
+            "tests/tests.c:12: The expression pop(s) == 3 is false.",
+            "tests/tests.c:13: size(s) should be 0"
+          ]
+        },
+        {
+          "name": "peek",
+          "assertions": 0,
+          "status": "ERRORED",
+          "messages": ["The test crashed."]
+        },
+        {
+          "name": "resize",
+          "assertions": 0,
+          "status": "SKIPPED",
+          "messages": ["not implemented"]
+        }
+      ]
+    }
+  ]
+}
+"#;
+
+    #[test]
+    fn detects_json() {
+        assert!(Criterion.detect(REPORT));
+        assert!(Criterion.detect(r#"{"id": "Criterion v2.4.2", "test_suites": []}"#));
+        assert!(!Criterion.detect(r#"{"testsuites": []}"#));
+    }
+
+    #[test]
+    fn parses_outcomes() {
+        let outcomes = Criterion.parse(REPORT).unwrap();
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [Status::Pass, Status::Fail, Status::Crash, Status::Skip]
+        );
+        assert_eq!(outcomes[0].suite, "stack");
+        assert_eq!(outcomes[3].reason.as_deref(), Some("not implemented"));
+        assert!(outcomes[3].failures.is_empty());
+    }
+
+    #[test]
+    fn parses_failures() {
+        let outcomes = Criterion.parse(REPORT).unwrap();
+        let failure = &outcomes[1].failures[0];
+        assert_eq!(failure.message, "The expression pop(s) == 3 is false.");
+        assert_eq!(failure.file.as_deref(), Some("tests/tests.c"));
+        assert_eq!(failure.line, Some(12));
+        assert_eq!(outcomes[1].failures[1].message, "size(s) should be 0");
+
+        // Crashes have no location
+        let failure = &outcomes[2].failures[0];
+        assert_eq!(failure.message, "The test crashed.");
+        assert_eq!(failure.file, None);
+    }
+}
@@ -0,0 +1,200 @@
+use super::{Element, TestFrameworkAdapter};
+use crate::gunit::TestError;
+use crate::report::{Failure, Status, TestOutcome};
+
+/// Name of the outcome of a suite whose initialization or cleanup failed
+const SUITE_FAILURE: &str = "(suite)";
+
+/// CUnit, through the XML report of its automated mode. A test failing several assertions has a
+/// record for each of them.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CUnit;
+
+impl TestFrameworkAdapter for CUnit {
+    fn detect(&self, content: &str) -> bool {
+        content.contains("<CUNIT_TEST_RUN_REPORT>")
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        let document = Element::parse(content)?;
+        if document.child("CUNIT_TEST_RUN_REPORT").is_none() {
+            return Err(TestError::Format("not a CUnit report".to_string()));
+        }
+
+        let mut outcomes: Vec<TestOutcome> = Vec::new();
+        for suite in document.descendants("CUNIT_RUN_SUITE") {
+            // A suite whose initialization or cleanup failed is reported as a test of its own,
+            // the tests it did not run being unknown
+            if let Some(failure) = suite.child("CUNIT_RUN_SUITE_FAILURE") {
+                let reason = text(failure, "FAILURE_REASON");
+                outcomes.push(TestOutcome {
+                    suite: text(failure, "SUITE_NAME"),
+                    name: SUITE_FAILURE.to_string(),
+                    status: Status::Fail,
+                    duration: None,
+                    file: None,
+                    line: None,
+                    failures: vec![Failure {
+                        message: match reason.as_str() {
+                            "" => "Suite failed".to_string(),
+                            _ => reason,
+                        },
+                        file: None,
+                        line: None,
+                    }],
+                    reason: None,
+                });
+            }
+            let Some(run) = suite.child("CUNIT_RUN_SUITE_SUCCESS") else {
+                continue;
+            };
+            let suite = text(run, "SUITE_NAME");
+
+            for record in run.children("CUNIT_RUN_TEST_RECORD") {
+                let (result, status) = match record.child("CUNIT_RUN_TEST_SUCCESS") {
+                    Some(result) => (result, Status::Pass),
+                    None => match record.child("CUNIT_RUN_TEST_FAILURE") {
+                        Some(result) => (result, Status::Fail),
+                        None => continue,
+                    },
+                };
+                let name = text(result, "TEST_NAME");
+
+                let index = match outcomes
+                    .iter()
+                    .position(|test| test.suite == suite && test.name == name)
+                {
+                    Some(index) => index,
+                    None => {
+                        outcomes.push(TestOutcome {
+                            suite: suite.clone(),
+                            name,
+                            status,
+                            duration: None,
+                            file: None,
+                            line: None,
+                            failures: Vec::new(),
+                            reason: None,
+                        });
+                        outcomes.len() - 1
+                    }
+                };
+
+                if status == Status::Fail {
+                    let test = &mut outcomes[index];
+                    test.status = Status::Fail;
+                    test.failures.push(Failure {
+                        message: text(result, "CONDITION"),
+                        file: Some(text(result, "FILE_NAME")).filter(|file| !file.is_empty()),
+                        line: text(result, "LINE_NUMBER").parse().ok(),
+                    });
+                }
+            }
+        }
+        Ok(outcomes)
+    }
+}
+
+/// The trimmed text of the child `name` of `element`
+fn text(element: &Element, name: &str) -> String {
+    element
+        .child(name)
+        .map(|child| child.text.trim().to_string())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `CUnit-Results.xml` of `CU_automated_run_tests`, with CUnit 2.1-3
+    const REPORT: &str = r#"<?xml version="1.0" ?>
+<?xml-stylesheet type="text/xsl" href="CUnit-Run.xsl" ?>
+<!DOCTYPE CUNIT_TEST_RUN_REPORT SYSTEM "CUnit-Run.dtd">
+<CUNIT_TEST_RUN_REPORT>
+  <CUNIT_HEADER/>
+  <CUNIT_RESULT_LISTING>
+    <CUNIT_RUN_SUITE>
+      <CUNIT_RUN_SUITE_SUCCESS>
+        <SUITE_NAME> Suite_1 </SUITE_NAME>
+        <CUNIT_RUN_TEST_RECORD>
+          <CUNIT_RUN_TEST_SUCCESS>
+            <TEST_NAME> test_of_fprintf </TEST_NAME>
+          </CUNIT_RUN_TEST_SUCCESS>
+        </CUNIT_RUN_TEST_RECORD>
+        <CUNIT_RUN_TEST_RECORD>
+          <CUNIT_RUN_TEST_FAILURE>
+            <TEST_NAME> test_of_fread </TEST_NAME>
+            <FILE_NAME> /app/tests/tests.c </FILE_NAME>
+            <LINE_NUMBER> 9 </LINE_NUMBER>
+            <CONDITION> CU_ASSERT_EQUAL(x,1) </CONDITION>
+          </CUNIT_RUN_TEST_FAILURE>
+        </CUNIT_RUN_TEST_RECORD>
+        <CUNIT_RUN_TEST_RECORD>
+          <CUNIT_RUN_TEST_FAILURE>
+            <TEST_NAME> test_of_fread </TEST_NAME>
+            <FILE_NAME> /app/tests/tests.c </FILE_NAME>
+            <LINE_NUMBER> 10 </LINE_NUMBER>
+            <CONDITION> CU_ASSERT_EQUAL(y,2) </CONDITION>
+          </CUNIT_RUN_TEST_FAILURE>
+        </CUNIT_RUN_TEST_RECORD>
+      </CUNIT_RUN_SUITE_SUCCESS>
+    </CUNIT_RUN_SUITE>
+    <CUNIT_RUN_SUITE>
+      <CUNIT_RUN_SUITE_FAILURE>
+        <SUITE_NAME> Suite_2 </SUITE_NAME>
+        <FAILURE_REASON> Suite Initialization Failed </FAILURE_REASON>
+      </CUNIT_RUN_SUITE_FAILURE>
+    </CUNIT_RUN_SUITE>
+  </CUNIT_RESULT_LISTING>
+  <CUNIT_RUN_SUMMARY>
+    <CUNIT_RUN_SUMMARY_RECORD>
+      <TYPE> Suites </TYPE>
+      <TOTAL> 2 </TOTAL>
+      <RUN> 1 </RUN>
+      <SUCCEEDED> - NA - </SUCCEEDED>
+      <FAILED> 1 </FAILED>
+      <INACTIVE> 0 </INACTIVE>
+    </CUNIT_RUN_SUMMARY_RECORD>
+  </CUNIT_RUN_SUMMARY>
+  <CUNIT_FOOTER> File Generated By CUnit v2.1-3 - Tue May 14 09:12:03 2024
+ </CUNIT_FOOTER>
+</CUNIT_TEST_RUN_REPORT>
+"#;
+
+    #[test]
+    fn detects_cunit() {
+        assert!(CUnit.detect(REPORT));
+        assert!(!CUnit.detect("<testsuites/>"));
+        assert!(CUnit.parse("<testsuites/>").is_err());
+    }
+
+    #[test]
+    fn merges_the_records_of_a_test() {
+        let outcomes = CUnit.parse(REPORT).unwrap();
+        assert_eq!(outcomes[0].full_name(), "Suite_1.test_of_fprintf");
+        assert_eq!(outcomes[0].status, Status::Pass);
+
+        let fread = &outcomes[1];
+        assert_eq!(fread.status, Status::Fail);
+        assert_eq!(fread.failures.len(), 2);
+        assert_eq!(fread.failures[1].message, "CU_ASSERT_EQUAL(y,2)");
+        assert_eq!(
+            fread.failures[1].file.as_deref(),
+            Some("/app/tests/tests.c")
+        );
+        assert_eq!(fread.failures[1].line, Some(10));
+    }
+
+    #[test]
+    fn reports_failed_suites() {
+        let outcomes = CUnit.parse(REPORT).unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[2].full_name(), "Suite_2.(suite)");
+        assert_eq!(outcomes[2].status, Status::Fail);
+        assert_eq!(
+            outcomes[2].failures[0].message,
+            "Suite Initialization Failed"
+        );
+    }
+}
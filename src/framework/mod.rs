@@ -0,0 +1,358 @@
+//! Adapters reading the results of the supported test frameworks into [`TestOutcome`]s.
+//!
+//! The framework of an exercise is the `testFramework` of its assignment, or is detected from
+//! the results file when the assignment does not tell.
+
+mod catch2;
+mod criterion;
+mod cunit;
+mod tap;
+mod unity;
+
+use crate::gunit::{TestError, UnitTest};
+use crate::report::TestOutcome;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+pub use catch2::Catch2;
+pub use criterion::Criterion;
+pub use cunit::CUnit;
+pub use tap::Tap;
+pub use unity::Unity;
+
+/// Reads the results file of a test framework
+pub trait TestFrameworkAdapter {
+    /// Whether `content` looks like a results file of this framework
+    fn detect(&self, content: &str) -> bool;
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum TestFramework {
+    /// JSON report of `--gtest_output=json`
+    Googletest,
+
+    /// JSON report of `--json`
+    Criterion,
+
+    /// XML report of the JUnit reporter, `--reporter junit`
+    Catch2,
+
+    /// XML report of the automated mode, `CUnit-Results.xml`
+    Cunit,
+
+    /// Text output of the test runner
+    Unity,
+
+    /// Test Anything Protocol, as written by shell scripts and many test harnesses
+    Tap,
+}
+
+impl TestFramework {
+    /// The frameworks in the order they are detected, the most specific formats first
+    const ALL: [TestFramework; 6] = [
+        TestFramework::Googletest,
+        TestFramework::Criterion,
+        TestFramework::Cunit,
+        TestFramework::Catch2,
+        TestFramework::Tap,
+        TestFramework::Unity,
+    ];
+
+    pub fn adapter(self) -> &'static dyn TestFrameworkAdapter {
+        match self {
+            TestFramework::Googletest => &Googletest,
+            TestFramework::Criterion => &Criterion,
+            TestFramework::Catch2 => &Catch2,
+            TestFramework::Cunit => &CUnit,
+            TestFramework::Unity => &Unity,
+            TestFramework::Tap => &Tap,
+        }
+    }
+
+    /// The framework that wrote `content`, if any adapter recognizes it
+    pub fn detect(content: &str) -> Option<TestFramework> {
+        Self::ALL
+            .into_iter()
+            .find(|framework| framework.adapter().detect(content))
+    }
+}
+
+/// Read the results file at `path`, written by `framework` or by the one detected
+pub fn read_tests(
+    path: &Path,
+    framework: Option<TestFramework>,
+) -> Result<Vec<TestOutcome>, TestError> {
+    let content = std::fs::read_to_string(path)?;
+    let framework = framework
+        .or_else(|| TestFramework::detect(&content))
+        .unwrap_or(TestFramework::Googletest);
+    log::debug!("reading {} as {framework:?} results", path.display());
+    framework.adapter().parse(&content)
+}
+
+/// googletest, see [`UnitTest`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Googletest;
+
+impl TestFrameworkAdapter for Googletest {
+    fn detect(&self, content: &str) -> bool {
+        serde_json::from_str::<serde_json::Value>(content).is_ok_and(|json| {
+            json.get("testsuites")
+                .is_some_and(|suites| suites.is_array())
+        })
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        Ok(serde_json::from_str::<UnitTest>(content)?.outcomes())
+    }
+}
+
+/// An element of an XML document, with the text it directly contains
+#[derive(Debug, Clone, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    children: Vec<Element>,
+}
+
+impl Element {
+    fn parse(content: &str) -> Result<Element, TestError> {
+        let invalid = |err: quick_xml::Error| TestError::Format(err.to_string());
+        let mut reader = Reader::from_str(content);
+        let mut stack = vec![Element::default()];
+        loop {
+            match reader.read_event().map_err(invalid)? {
+                Event::Start(start) => stack.push(Element::new(&start)?),
+                Event::Empty(empty) => {
+                    let element = Element::new(&empty)?;
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Event::End(_) => {
+                    let element = stack.pop().unwrap();
+                    let Some(parent) = stack.last_mut() else {
+                        return Err(TestError::Format("unbalanced closing tag".to_string()));
+                    };
+                    parent.children.push(element);
+                }
+                Event::Text(text) => {
+                    let text = text.unescape().map_err(invalid)?;
+                    stack.last_mut().unwrap().text.push_str(&text);
+                }
+                Event::CData(data) => {
+                    let data = String::from_utf8_lossy(&data.into_inner()).into_owned();
+                    stack.last_mut().unwrap().text.push_str(&data);
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        match <[Element; 1]>::try_from(stack) {
+            Ok([document]) => Ok(document),
+            Err(_) => Err(TestError::Format("unclosed tag".to_string())),
+        }
+    }
+
+    fn new(start: &quick_xml::events::BytesStart) -> Result<Element, TestError> {
+        let attributes = start
+            .attributes()
+            .map(|attribute| {
+                let attribute = attribute.map_err(|err| TestError::Format(err.to_string()))?;
+                let value = attribute
+                    .unescape_value()
+                    .map_err(|err| TestError::Format(err.to_string()))?;
+                Ok((
+                    String::from_utf8_lossy(attribute.key.as_ref()).into_owned(),
+                    value.into_owned(),
+                ))
+            })
+            .collect::<Result<_, TestError>>()?;
+
+        Ok(Element {
+            name: String::from_utf8_lossy(start.name().as_ref()).into_owned(),
+            attributes,
+            ..Element::default()
+        })
+    }
+
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    fn child(&self, name: &str) -> Option<&Element> {
+        self.children.iter().find(|child| child.name == name)
+    }
+
+    fn children<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Element> {
+        self.children.iter().filter(move |child| child.name == name)
+    }
+
+    /// Every element named `name` of the tree, in document order
+    fn descendants<'a>(&'a self, name: &'a str) -> Vec<&'a Element> {
+        let mut found = Vec::new();
+        for child in &self.children {
+            if child.name == name {
+                found.push(child);
+            }
+            found.extend(child.descendants(name));
+        }
+        found
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::Status;
+    use std::time::Duration;
+
+    /// Report of `--gtest_output=json` with googletest 1.14
+    const REPORT: &str = r#"{
+  "tests": 4,
+  "failures": 1,
+  "disabled": 1,
+  "errors": 0,
+  "timestamp": "2024-05-14T09:12:03Z",
+  "time": "0.003s",
+  "name": "AllTests",
+  "testsuites": [
+    {
+      "name": "Add",
+      "tests": 4,
+      "failures": 1,
+      "disabled": 1,
+      "errors": 0,
+      "timestamp": "2024-05-14T09:12:03Z",
+      "time": "0.002s",
+      "testsuite": [
+        {
+          "name": "Positive",
+          "file": "\/app\/tests\/tests.cpp",
+          "line": 5,
+          "status": "RUN",
+          "result": "COMPLETED",
+          "timestamp": "2024-05-14T09:12:03Z",
+          "time": "0.002s",
+          "classname": "Add"
+        },
+        {
+          "name": "Negative",
+          "file": "\/app\/tests\/tests.cpp",
+          "line": 9,
+          "status": "RUN",
+          "result": "COMPLETED",
+          "timestamp": "2024-05-14T09:12:03Z",
+          "time": "0s",
+          "classname": "Add",
+          "failures": [
+            {
+              "failure": "\/app\/tests\/tests.cpp:10\nExpected equality of these values:\n  add(-1, -2)\n    Which is: -1\n  -3\n",
+              "type": ""
+            }
+          ]
+        },
+        {
+          "name": "Later",
+          "file": "\/app\/tests\/tests.cpp",
+          "line": 19,
+          "status": "RUN",
+          "result": "SKIPPED",
+          "timestamp": "2024-05-14T09:12:03Z",
+          "time": "0s",
+          "classname": "Add",
+          "skipped": [
+            {
+              "message": "\/app\/tests\/tests.cpp:20\nnot yet\n"
+            }
+          ]
+        },
+        {
+          "name": "DISABLED_Overflow",
+          "file": "\/app\/tests\/tests.cpp",
+          "line": 24,
+          "status": "NOTRUN",
+          "result": "SUPPRESSED",
+          "timestamp": "2024-05-14T09:12:03Z",
+          "time": "0s",
+          "classname": "Add"
+        }
+      ]
+    }
+  ]
+}
+"#;
+
+    #[test]
+    fn parses_googletest() {
+        let outcomes = Googletest.parse(REPORT).unwrap();
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [Status::Pass, Status::Fail, Status::Skip, Status::Disabled]
+        );
+        assert_eq!(outcomes[0].suite, "Add");
+        assert_eq!(outcomes[0].file.as_deref(), Some("/app/tests/tests.cpp"));
+        assert_eq!(outcomes[0].duration, Some(Duration::from_millis(2)));
+
+        let failure = &outcomes[1].failures[0];
+        assert_eq!(failure.line, Some(10));
+        assert!(failure.message.starts_with("Expected equality"));
+        assert_eq!(
+            outcomes[2].reason.as_deref(),
+            Some("/app/tests/tests.cpp:20\nnot yet")
+        );
+        assert_eq!(outcomes[3].reason.as_deref(), Some("test disabled"));
+    }
+
+    #[test]
+    fn detects_the_framework() {
+        let detect = TestFramework::detect;
+        assert_eq!(detect(REPORT), Some(TestFramework::Googletest));
+        assert_eq!(
+            detect(r#"{"id": "Criterion v2.4.2", "test_suites": []}"#),
+            Some(TestFramework::Criterion)
+        );
+        assert_eq!(
+            detect("<CUNIT_TEST_RUN_REPORT>\n</CUNIT_TEST_RUN_REPORT>"),
+            Some(TestFramework::Cunit)
+        );
+        assert_eq!(
+            detect("<?xml version=\"1.0\"?>\n<testsuites><testsuite name=\"t\"/></testsuites>"),
+            Some(TestFramework::Catch2)
+        );
+        assert_eq!(detect("1..1\nok 1 - adds\n"), Some(TestFramework::Tap));
+        assert_eq!(
+            detect("tests/test_add.c:3:test_add:PASS\n"),
+            Some(TestFramework::Unity)
+        );
+        assert_eq!(detect("Segmentation fault"), None);
+    }
+
+    #[test]
+    fn parses_xml() {
+        let document =
+            Element::parse("<a x=\"1 &amp; 2\"><b>text</b><b><![CDATA[<raw>]]></b><c/></a>")
+                .unwrap();
+        let root = &document.children[0];
+        assert_eq!(root.attribute("x"), Some("1 & 2"));
+        let texts = root
+            .children("b")
+            .map(|b| b.text.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(texts, ["text", "<raw>"]);
+        assert!(root.child("c").is_some());
+
+        assert!(Element::parse("<a><b></a>").is_err());
+        assert!(Element::parse("<a>").is_err());
+    }
+}
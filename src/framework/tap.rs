@@ -0,0 +1,335 @@
+use super::TestFrameworkAdapter;
+use crate::gunit::TestError;
+use crate::report::{Failure, Status, TestOutcome};
+use indent::indent_all_by;
+use serde_yaml::Value;
+use std::time::Duration;
+
+/// The Test Anything Protocol, versions 13 and 14. The results of subtests are summarized by the
+/// test point of their parent, and the YAML diagnostics of a failed test become its failure.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Tap;
+
+impl TestFrameworkAdapter for Tap {
+    fn detect(&self, content: &str) -> bool {
+        content
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .is_some_and(|line| {
+                line.starts_with("TAP version ")
+                    || plan(line).is_some()
+                    || test_point(line).is_some()
+            })
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        let mut outcomes: Vec<TestOutcome> = Vec::new();
+        let mut planned = None;
+        let mut bailed_out = None;
+        let mut lines = content.lines().peekable();
+        while let Some(line) = lines.next() {
+            if let Some(count) = plan(line) {
+                planned = Some(count);
+            } else if let Some(reason) = line.strip_prefix("Bail out!") {
+                bailed_out = Some(reason.trim().to_string());
+                break;
+            } else if let Some(mut outcome) = test_point(line) {
+                // The diagnostics of a test point follow it, indented
+                if lines.peek().is_some_and(|line| line.trim() == "---") {
+                    let start = lines.next().unwrap_or_default();
+                    let indent = start.len() - start.trim_start().len();
+                    let mut yaml = String::new();
+                    for line in lines.by_ref() {
+                        if line.trim() == "..." {
+                            break;
+                        }
+                        yaml.push_str(line.get(indent..).unwrap_or(line.trim_start()));
+                        yaml.push('\n');
+                    }
+                    if let Ok(diagnostics) = serde_yaml::from_str::<Value>(&yaml) {
+                        diagnose(&mut outcome, &diagnostics);
+                    }
+                }
+                if outcome.name.is_empty() {
+                    outcome.name = format!("test {}", outcomes.len() + 1);
+                }
+                outcomes.push(outcome);
+            }
+        }
+
+        if outcomes.is_empty() && planned.is_none() {
+            return Err(TestError::Format("no TAP test point found".to_string()));
+        }
+
+        // Tests never reported, after bailing out or crashing
+        for number in outcomes.len() + 1..=planned.unwrap_or_default() {
+            outcomes.push(TestOutcome {
+                suite: String::new(),
+                name: format!("test {number}"),
+                status: Status::Crash,
+                duration: None,
+                file: None,
+                line: None,
+                failures: Vec::new(),
+                reason: Some(match &bailed_out {
+                    Some(reason) if !reason.is_empty() => format!("bailed out: {reason}"),
+                    Some(_) => "bailed out".to_string(),
+                    None => "not reported".to_string(),
+                }),
+            });
+        }
+        Ok(outcomes)
+    }
+}
+
+/// The number of tests of a plan line, e.g. `1..4` or `1..0 # SKIP no tests`. The indented plans
+/// are the ones of subtests.
+fn plan(line: &str) -> Option<usize> {
+    let (first, count) = line.split('#').next()?.trim_end().split_once("..")?;
+    (first == "1").then_some(())?;
+    count.parse().ok()
+}
+
+/// A test point, e.g. `not ok 2 - description # TODO reason`
+fn test_point(line: &str) -> Option<TestOutcome> {
+    let (passed, rest) = match line.strip_prefix("not ok") {
+        Some(rest) => (false, rest),
+        None => (true, line.strip_prefix("ok")?),
+    };
+    if !(rest.is_empty() || rest.starts_with(' ')) {
+        return None;
+    }
+
+    // A `#` of the description is escaped as `\\#`
+    let directive_start = rest
+        .match_indices(" #")
+        .map(|(index, _)| index)
+        .find(|&index| !rest[..index].ends_with('\\'));
+    let (description, directive) = match directive_start {
+        Some(index) => (&rest[..index], Some(rest[index + 2..].trim())),
+        None => (rest, None),
+    };
+    let description = description.replace("\\#", "#");
+    let description = description
+        .trim()
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start()
+        .trim_start_matches("- ")
+        .trim();
+    let number = rest.trim().split(' ').next().unwrap_or_default();
+
+    // Directives are case insensitive, and `# skipped` is as common as `# SKIP`
+    let directive = directive.and_then(|directive| {
+        let (word, reason) = directive
+            .split_once(char::is_whitespace)
+            .unwrap_or((directive, ""));
+        let word = word.to_ascii_uppercase();
+        ["SKIP", "TODO"]
+            .into_iter()
+            .find(|kind| word.starts_with(kind))
+            .map(|kind| (kind, reason.trim()))
+    });
+    let (status, reason) = match directive {
+        Some(("SKIP", reason)) => (Status::Skip, reason.to_string()),
+        // A failing test still to be written is expected to fail
+        Some((_, reason)) if !passed => (Status::Skip, format!("TODO {reason}")),
+        _ if passed => (Status::Pass, String::new()),
+        _ => (Status::Fail, String::new()),
+    };
+
+    Some(TestOutcome {
+        suite: String::new(),
+        // Named after its position by the caller when it has no number either
+        name: match (description, number) {
+            ("", "") => String::new(),
+            ("", number) => format!("test {number}"),
+            (description, _) => description.to_string(),
+        },
+        status,
+        duration: None,
+        file: None,
+        line: None,
+        failures: Vec::new(),
+        reason: Some(reason.trim().to_string()).filter(|reason| !reason.is_empty()),
+    })
+}
+
+/// Fill `outcome` with its YAML diagnostics, following the conventions of the TAP producers:
+/// `message`, `at` or `file` and `line`, `duration_ms`, the other keys being details
+fn diagnose(outcome: &mut TestOutcome, diagnostics: &Value) {
+    let Value::Mapping(diagnostics) = diagnostics else {
+        return;
+    };
+    let text = |value: &Value| match value {
+        Value::String(text) => Some(text.clone()),
+        Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    };
+
+    let at = diagnostics.get("at").unwrap_or(&Value::Null);
+    let file = at
+        .get("file")
+        .or_else(|| diagnostics.get("file"))
+        .and_then(text);
+    let line = at
+        .get("line")
+        .or_else(|| diagnostics.get("line"))
+        .and_then(text)
+        .and_then(|line| line.parse().ok());
+    outcome.duration = diagnostics
+        .get("duration_ms")
+        .and_then(Value::as_f64)
+        .and_then(|ms| Duration::try_from_secs_f64(ms / 1000.0).ok());
+
+    if outcome.status != Status::Fail {
+        return;
+    }
+
+    let mut message = diagnostics
+        .get("message")
+        .and_then(text)
+        .unwrap_or_default();
+    for (key, value) in diagnostics {
+        let Some(key) = key.as_str() else {
+            continue;
+        };
+        if ["message", "at", "file", "line", "duration_ms", "severity"].contains(&key) {
+            continue;
+        }
+        let value = serde_yaml::to_string(value).unwrap_or_default();
+        let value = value.trim_end();
+        if value.contains('\n') {
+            message.push_str(&format!("\n{key}:\n{}", indent_all_by(2, value)));
+        } else {
+            message.push_str(&format!("\n{key}: {value}"));
+        }
+    }
+
+    outcome.failures.push(Failure {
+        message: message.trim().to_string(),
+        file,
+        line,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of `bats --tap`
+    const BATS: &str = "\
+1..3
+ok 1 addition using bc
+not ok 2 addition using dc
+# (in test file test.bats, line 9)
+#   `[ \"$result\" -eq 4 ]' failed
+ok 3 subtraction # skip not implemented
+";
+
+    /// Output of `node --test --test-reporter=tap`, with a subtest and a crash
+    const NODE: &str = "\
+TAP version 13
+# Subtest: math
+    # Subtest: adds
+    ok 1 - adds
+      ---
+      duration_ms: 0.3
+      ...
+    1..1
+ok 1 - math
+  ---
+  duration_ms: 1.52
+  type: 'suite'
+  ...
+# Subtest: divides
+not ok 2 - divides
+  ---
+  duration_ms: 0.8
+  failureType: 'testCodeFailure'
+  error: |-
+    Expected values to be strictly equal:
+
+    1 !== 2
+  code: 'ERR_ASSERTION'
+  location: '/app/test.js:8:3'
+  message: 'Expected values to be strictly equal'
+  at:
+    file: '/app/test.js'
+    line: 8
+  ...
+1..4
+";
+
+    #[test]
+    fn detects_tap() {
+        assert!(Tap.detect(BATS));
+        assert!(Tap.detect(NODE));
+        assert!(!Tap.detect("{\"testsuites\": []}"));
+        assert!(!Tap.detect("tests.c:12:test_add:PASS\n"));
+    }
+
+    #[test]
+    fn parses_bats() {
+        let outcomes = Tap.parse(BATS).unwrap();
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(statuses, [Status::Pass, Status::Fail, Status::Skip]);
+        assert_eq!(outcomes[1].name, "addition using dc");
+        assert_eq!(outcomes[2].reason.as_deref(), Some("not implemented"));
+    }
+
+    #[test]
+    fn parses_yaml_diagnostics() {
+        let outcomes = Tap.parse(NODE).unwrap();
+        assert_eq!(outcomes[0].name, "math");
+        assert_eq!(outcomes[0].duration, Some(Duration::from_micros(1520)));
+
+        let divides = &outcomes[1];
+        assert_eq!(divides.status, Status::Fail);
+        assert_eq!(divides.file.as_deref(), None);
+        let failure = &divides.failures[0];
+        assert!(failure
+            .message
+            .starts_with("Expected values to be strictly equal\n"));
+        assert!(failure.message.contains("code: ERR_ASSERTION"));
+        assert_eq!(failure.file.as_deref(), Some("/app/test.js"));
+        assert_eq!(failure.line, Some(8));
+    }
+
+    #[test]
+    fn ignores_the_plans_of_subtests() {
+        // The tests planned but never reported are the ones that did not run
+        let outcomes = Tap.parse(NODE).unwrap();
+        assert_eq!(outcomes.len(), 4);
+        assert_eq!(outcomes[2].status, Status::Crash);
+        assert_eq!(outcomes[2].reason.as_deref(), Some("not reported"));
+        assert_eq!(plan("    1..1"), None);
+        assert_eq!(plan("1..0 # SKIP no tests"), Some(0));
+    }
+
+    #[test]
+    fn reports_bail_out() {
+        let outcomes = Tap
+            .parse("1..3\nok 1 - first\nBail out! database down\n")
+            .unwrap();
+        assert_eq!(outcomes.len(), 3);
+        assert_eq!(outcomes[1].status, Status::Crash);
+        assert_eq!(
+            outcomes[1].reason.as_deref(),
+            Some("bailed out: database down")
+        );
+    }
+
+    #[test]
+    fn parses_directives() {
+        let todo = test_point("not ok 4 - divides # TODO not written").unwrap();
+        assert_eq!(todo.status, Status::Skip);
+        assert_eq!(todo.reason.as_deref(), Some("TODO not written"));
+
+        let escaped = test_point("ok 3 - handles \\# in names # SKIP later").unwrap();
+        assert_eq!(escaped.name, "handles # in names");
+        assert_eq!(escaped.status, Status::Skip);
+
+        assert!(test_point("okay").is_none());
+        assert_eq!(Tap.parse("1..2\nok\nok\n").unwrap()[1].name, "test 2");
+    }
+}
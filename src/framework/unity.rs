@@ -0,0 +1,122 @@
+use super::TestFrameworkAdapter;
+use crate::gunit::TestError;
+use crate::report::{Failure, Status, TestOutcome};
+use std::path::Path;
+
+/// Unity, through the output of its test runner, a `file:line:test:RESULT[: message]` line per
+/// test. Tests are grouped in suites after their file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Unity;
+
+impl TestFrameworkAdapter for Unity {
+    fn detect(&self, content: &str) -> bool {
+        content.lines().any(|line| result(line).is_some())
+    }
+
+    fn parse(&self, content: &str) -> Result<Vec<TestOutcome>, TestError> {
+        let outcomes = content.lines().filter_map(result).collect::<Vec<_>>();
+        if outcomes.is_empty() {
+            return Err(TestError::Format("no Unity test result found".to_string()));
+        }
+        Ok(outcomes)
+    }
+}
+
+/// The test of a line of the output, if it is a test result
+fn result(line: &str) -> Option<TestOutcome> {
+    let mut parts = line.splitn(4, ':');
+    let (file, number, name, rest) = (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+    let number: u32 = number.parse().ok()?;
+    let (result, message) = rest
+        .split_once(": ")
+        .map_or((rest, None), |(result, message)| (result, Some(message)));
+
+    let status = match result.trim() {
+        "PASS" => Status::Pass,
+        "FAIL" => Status::Fail,
+        "IGNORE" => Status::Skip,
+        _ => return None,
+    };
+    let suite = Path::new(file)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    Some(TestOutcome {
+        suite,
+        name: name.to_string(),
+        status,
+        duration: None,
+        file: Some(file.to_string()),
+        line: Some(number),
+        failures: match status {
+            Status::Fail => vec![Failure {
+                message: message.unwrap_or_default().to_string(),
+                file: Some(file.to_string()),
+                line: Some(number),
+            }],
+            _ => Vec::new(),
+        },
+        reason: match status {
+            Status::Skip => message.map(str::to_string),
+            _ => None,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Output of a test runner of Unity 2.6
+    const OUTPUT: &str = "\
+tests/test_stack.c:12:test_push:PASS
+tests/test_stack.c:18:test_pop:FAIL: Expected 3 Was 2
+tests/test_stack.c:25:test_peek:FAIL: Expected 'a:b' Was 'a'
+tests/test_stack.c:31:test_resize:IGNORE: not implemented
+tests/test_stack.c:36:test_clear:PASS
+
+-----------------------
+5 Tests 2 Failures 1 Ignored
+FAIL
+";
+
+    #[test]
+    fn detects_results() {
+        assert!(Unity.detect(OUTPUT));
+        assert!(!Unity.detect("-----------------------\n0 Tests 0 Failures 0 Ignored\nOK\n"));
+        assert!(Unity.parse("make: Nothing to be done for 'all'.").is_err());
+    }
+
+    #[test]
+    fn parses_outcomes() {
+        let outcomes = Unity.parse(OUTPUT).unwrap();
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [
+                Status::Pass,
+                Status::Fail,
+                Status::Fail,
+                Status::Skip,
+                Status::Pass
+            ]
+        );
+        assert_eq!(outcomes[0].suite, "test_stack");
+        assert_eq!(outcomes[0].name, "test_push");
+        assert_eq!(outcomes[0].file.as_deref(), Some("tests/test_stack.c"));
+        assert_eq!(outcomes[0].line, Some(12));
+        assert_eq!(outcomes[3].reason.as_deref(), Some("not implemented"));
+    }
+
+    #[test]
+    fn parses_failures() {
+        let outcomes = Unity.parse(OUTPUT).unwrap();
+        let failure = &outcomes[1].failures[0];
+        assert_eq!(failure.message, "Expected 3 Was 2");
+        assert_eq!(failure.line, Some(18));
+
+        // Only the first colons separate the fields
+        assert_eq!(outcomes[2].failures[0].message, "Expected 'a:b' Was 'a'");
+    }
+}
@@ -0,0 +1,619 @@
+use base64::Engine;
+use serde::de::{DeserializeOwned, IgnoredAny};
+use serde::{Deserialize, Serialize};
+use std::io::Read;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+pub const DEFAULT_HOST: &str = "https://gitlab.com";
+
+/// Size of the pages requested from paginated endpoints, the maximum allowed by GitLab
+const PAGE_SIZE: usize = 100;
+
+#[derive(Debug, Error)]
+pub enum GitLabError {
+    #[error("unable to reach GitLab: {0}")]
+    Transport(Box<ureq::Transport>),
+
+    #[error("GitLab responded with {0}: {1}")]
+    Status(u16, String),
+
+    #[error("invalid response from GitLab: {0}")]
+    Response(#[from] std::io::Error),
+
+    #[error("the device authorization expired, please try again")]
+    Expired,
+
+    #[error("the device authorization was denied")]
+    Denied,
+
+    #[error("the fork of {0} failed: {1}")]
+    ForkFailed(String, String),
+
+    #[error("the fork {0} is still being copied after {1}s, try again later")]
+    ForkTimeout(String, u64),
+}
+
+impl From<ureq::Error> for GitLabError {
+    fn from(error: ureq::Error) -> Self {
+        match error {
+            ureq::Error::Status(status, response) => {
+                let message = response
+                    .into_json::<ErrorResponse>()
+                    .map(|error| error.to_string())
+                    .unwrap_or_default();
+                GitLabError::Status(status, message)
+            }
+            ureq::Error::Transport(transport) => GitLabError::Transport(Box::new(transport)),
+        }
+    }
+}
+
+/// The shapes of the errors returned by the REST API and by the OAuth endpoints
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    message: Option<serde_json::Value>,
+    error: Option<String>,
+    error_description: Option<String>,
+}
+
+impl std::fmt::Display for ErrorResponse {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (&self.message, &self.error, &self.error_description) {
+            (Some(serde_json::Value::String(message)), _, _) => write!(f, "{message}"),
+            (Some(message), _, _) => write!(f, "{message}"),
+            (_, _, Some(description)) => write!(f, "{description}"),
+            (_, Some(error), _) => write!(f, "{error}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct User {
+    pub id: u64,
+    pub username: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Project {
+    pub id: u64,
+    pub name: String,
+    pub path_with_namespace: String,
+    pub description: Option<String>,
+    pub http_url_to_repo: String,
+
+    /// State of the copy of the repository of a fork, `finished` once it can be cloned
+    pub import_status: Option<String>,
+    pub import_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Pipeline {
+    pub id: u64,
+    pub status: String,
+    pub sha: String,
+    pub web_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Job {
+    pub id: u64,
+
+    /// The archive of the artifacts, if the job kept any
+    pub artifacts_file: Option<IgnoredAny>,
+}
+
+/// Response to the start of an OAuth device authorization
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeviceAuthorization {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    pub interval: Option<u64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct OAuthToken {
+    pub access_token: String,
+}
+
+/// A client for the REST API and the OAuth endpoints of a GitLab instance
+#[derive(Debug, Clone)]
+pub struct GitLab {
+    host: String,
+    token: Option<String>,
+    agent: ureq::Agent,
+}
+
+impl GitLab {
+    pub fn new(host: &str) -> Self {
+        Self {
+            host: host.trim_end_matches('/').to_string(),
+            token: None,
+            agent: ureq::AgentBuilder::new()
+                .timeout(Duration::from_secs(30))
+                .build(),
+        }
+    }
+
+    pub fn with_token(mut self, token: &str) -> Self {
+        self.token = Some(token.to_string());
+        self
+    }
+
+    fn request(&self, method: &str, path: &str) -> ureq::Request {
+        let request = self.agent.request(method, &format!("{}{path}", self.host));
+        match &self.token {
+            Some(token) => request.set("Authorization", &format!("Bearer {token}")),
+            None => request,
+        }
+    }
+
+    pub(crate) fn get<T: DeserializeOwned>(&self, path: &str) -> Result<T, GitLabError> {
+        Ok(self.request("GET", path).call()?.into_json()?)
+    }
+
+    /// Every item of a paginated endpoint
+    fn get_all<T: DeserializeOwned>(&self, path: &str) -> Result<Vec<T>, GitLabError> {
+        let separator = if path.contains('?') { '&' } else { '?' };
+        let mut items = Vec::new();
+        for page in 1.. {
+            let batch: Vec<T> = self.get(&format!(
+                "{path}{separator}per_page={PAGE_SIZE}&page={page}"
+            ))?;
+            let last = batch.len() < PAGE_SIZE;
+            items.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(items)
+    }
+
+    /// Header authenticating git over HTTP with the token, which GitLab accepts as the password
+    /// of the `oauth2` user
+    pub fn git_auth_header(&self) -> Option<String> {
+        self.token.as_ref().map(|token| {
+            let credentials =
+                base64::engine::general_purpose::STANDARD.encode(format!("oauth2:{token}"));
+            format!("Authorization: Basic {credentials}")
+        })
+    }
+
+    /// The user the token belongs to
+    pub fn user(&self) -> Result<User, GitLabError> {
+        self.get("/api/v4/user")
+    }
+
+    /// A project, by ID or by path with its namespace
+    pub fn project(&self, project: &str) -> Result<Project, GitLabError> {
+        self.get(&format!("/api/v4/projects/{}", encode(project)))
+    }
+
+    /// The projects of a group and of its subgroups
+    pub fn group_projects(&self, group: &str) -> Result<Vec<Project>, GitLabError> {
+        self.get_all(&format!(
+            "/api/v4/groups/{}/projects?include_subgroups=true&archived=false&order_by=name&sort=asc",
+            encode(group)
+        ))
+    }
+
+    /// The forks of `project` owned by the user
+    pub fn owned_forks(&self, project: u64) -> Result<Vec<Project>, GitLabError> {
+        self.get_all(&format!("/api/v4/projects/{project}/forks?owned=true"))
+    }
+
+    /// Fork `project` in the namespace of the user, as a private project
+    pub fn fork(&self, project: u64) -> Result<Project, GitLabError> {
+        Ok(self
+            .request("POST", &format!("/api/v4/projects/{project}/fork"))
+            .send_form(&[("visibility", "private")])?
+            .into_json()?)
+    }
+
+    /// Wait for the repository of a fork to be copied, for `timeout` at most
+    pub fn wait_for_fork(
+        &self,
+        mut fork: Project,
+        timeout: Duration,
+    ) -> Result<Project, GitLabError> {
+        let start = Instant::now();
+        loop {
+            match fork.import_status.as_deref() {
+                None | Some("none") | Some("finished") => return Ok(fork),
+                Some("failed") => {
+                    return Err(GitLabError::ForkFailed(
+                        fork.path_with_namespace,
+                        fork.import_error.unwrap_or_default(),
+                    ))
+                }
+                _ if start.elapsed() >= timeout => {
+                    return Err(GitLabError::ForkTimeout(
+                        fork.path_with_namespace,
+                        timeout.as_secs(),
+                    ))
+                }
+                _ => {
+                    std::thread::sleep(Duration::from_secs(1));
+                    fork = self.get(&format!("/api/v4/projects/{}", fork.id))?;
+                }
+            }
+        }
+    }
+
+    /// The latest pipeline run for the branch `reference`
+    pub fn latest_pipeline(
+        &self,
+        project: &str,
+        reference: &str,
+    ) -> Result<Option<Pipeline>, GitLabError> {
+        let pipelines: Vec<Pipeline> = self.get(&format!(
+            "/api/v4/projects/{}/pipelines?ref={}&order_by=id&sort=desc&per_page=1",
+            encode(project),
+            reference.replace('/', "%2F")
+        ))?;
+        Ok(pipelines.into_iter().next())
+    }
+
+    pub fn pipeline_jobs(&self, project: &str, pipeline: u64) -> Result<Vec<Job>, GitLabError> {
+        self.get_all(&format!(
+            "/api/v4/projects/{}/pipelines/{pipeline}/jobs",
+            encode(project)
+        ))
+    }
+
+    /// A single file of the artifacts of a job, `None` if the job has no such file
+    pub fn job_artifact(
+        &self,
+        project: &str,
+        job: u64,
+        path: &str,
+    ) -> Result<Option<Vec<u8>>, GitLabError> {
+        let response = self
+            .request(
+                "GET",
+                &format!(
+                    "/api/v4/projects/{}/jobs/{job}/artifacts/{}",
+                    encode(project),
+                    path.trim_start_matches('/')
+                ),
+            )
+            .call();
+
+        match response {
+            Ok(response) => {
+                let mut content = Vec::new();
+                response.into_reader().read_to_end(&mut content)?;
+                Ok(Some(content))
+            }
+            Err(ureq::Error::Status(404, _)) => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Start an OAuth device authorization for the application `client_id`
+    pub fn authorize_device(
+        &self,
+        client_id: &str,
+        scope: &str,
+    ) -> Result<DeviceAuthorization, GitLabError> {
+        Ok(self
+            .request("POST", "/oauth/authorize_device")
+            .send_form(&[("client_id", client_id), ("scope", scope)])?
+            .into_json()?)
+    }
+
+    /// Wait for the user to accept the device authorization
+    pub fn poll_device_token(
+        &self,
+        client_id: &str,
+        authorization: &DeviceAuthorization,
+    ) -> Result<OAuthToken, GitLabError> {
+        let mut interval = authorization.interval.unwrap_or(5);
+        let mut remaining = authorization.expires_in;
+        loop {
+            std::thread::sleep(Duration::from_secs(interval));
+            remaining = remaining
+                .checked_sub(interval)
+                .ok_or(GitLabError::Expired)?;
+
+            let response = self.request("POST", "/oauth/token").send_form(&[
+                ("client_id", client_id),
+                ("device_code", &authorization.device_code),
+                ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ]);
+
+            match response {
+                Ok(response) => return Ok(response.into_json()?),
+                Err(ureq::Error::Status(status, response)) => {
+                    let error = response.into_json::<ErrorResponse>()?;
+                    match error.error.as_deref() {
+                        Some("authorization_pending") => {}
+                        Some("slow_down") => interval += 5,
+                        Some("expired_token") => return Err(GitLabError::Expired),
+                        Some("access_denied") => return Err(GitLabError::Denied),
+                        _ => return Err(GitLabError::Status(status, error.to_string())),
+                    }
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+    }
+
+    /// Revoke an OAuth access token
+    pub fn revoke_oauth_token(&self, client_id: &str, token: &str) -> Result<(), GitLabError> {
+        self.request("POST", "/oauth/revoke")
+            .send_form(&[("client_id", client_id), ("token", token)])?;
+        Ok(())
+    }
+
+    /// Revoke the personal access token used to authenticate
+    pub fn revoke_personal_access_token(&self) -> Result<(), GitLabError> {
+        self.request("DELETE", "/api/v4/personal_access_tokens/self")
+            .call()?;
+        Ok(())
+    }
+}
+
+/// Encode a project or group path to be used as an ID in a URL
+fn encode(path: &str) -> String {
+    path.trim_matches('/').replace('/', "%2F")
+}
+
+/// The path of the project a git remote points to, for both HTTP and SSH remotes
+pub fn project_path(remote: &str) -> Option<String> {
+    let path = match remote.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => remote.split_once(':')?.1,
+    };
+    let path = path.trim_matches('/').trim_end_matches(".git");
+    (!path.is_empty()).then(|| path.to_string())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// A GitLab instance on 127.0.0.1, answering each request with the first route matching its
+    /// method and path. A route answers once, unless it is the last one matching.
+    pub(crate) struct MockServer {
+        pub host: String,
+
+        /// The requests received, as their method, path, `Authorization` header and body
+        requests: Arc<Mutex<Vec<[String; 4]>>>,
+    }
+
+    impl MockServer {
+        pub fn start(routes: &[(&str, u16, &str)]) -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let host = format!("http://{}", listener.local_addr().unwrap());
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let mut routes = routes
+                .iter()
+                .map(|(route, status, body)| (route.to_string(), *status, body.to_string()))
+                .collect::<Vec<_>>();
+
+            let received = Arc::clone(&requests);
+            std::thread::spawn(move || {
+                for stream in listener.incoming() {
+                    let mut stream = stream.unwrap();
+                    let request = read_request(&mut BufReader::new(&stream));
+                    let route = format!("{} {}", request[0], request[1]);
+                    let matching = routes
+                        .iter()
+                        .enumerate()
+                        .filter(|(_, (path, _, _))| route.starts_with(path.as_str()))
+                        .map(|(index, _)| index)
+                        .collect::<Vec<_>>();
+                    let (status, body) = match matching[..] {
+                        [] => (404, r#"{"message":"404 Not Found"}"#.to_string()),
+                        [index] => (routes[index].1, routes[index].2.clone()),
+                        [index, ..] => {
+                            let (_, status, body) = routes.remove(index);
+                            (status, body)
+                        }
+                    };
+                    received.lock().unwrap().push(request);
+
+                    let _ = write!(
+                        stream,
+                        "HTTP/1.1 {status} Mock\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                }
+            });
+
+            Self { host, requests }
+        }
+
+        pub fn requests(&self) -> Vec<[String; 4]> {
+            self.requests.lock().unwrap().clone()
+        }
+    }
+
+    fn read_request(reader: &mut impl BufRead) -> [String; 4] {
+        let mut line = String::new();
+        reader.read_line(&mut line).unwrap();
+        let mut parts = line.split_whitespace().map(str::to_string);
+        let method = parts.next().unwrap_or_default();
+        let path = parts.next().unwrap_or_default();
+
+        let (mut authorization, mut length) = (String::new(), 0);
+        loop {
+            let mut header = String::new();
+            reader.read_line(&mut header).unwrap();
+            let Some((name, value)) = header.trim_end().split_once(": ") else {
+                break;
+            };
+            match name.to_ascii_lowercase().as_str() {
+                "authorization" => authorization = value.to_string(),
+                "content-length" => length = value.parse().unwrap(),
+                _ => {}
+            }
+        }
+
+        let mut body = vec![0; length];
+        reader.read_exact(&mut body).unwrap();
+        [
+            method,
+            path,
+            authorization,
+            String::from_utf8(body).unwrap(),
+        ]
+    }
+
+    const AUTHORIZATION: &str = r#"{
+  "device_code": "GmRhmhcxhwAzkoEqiMEg_DnyEysNkuNhszIySk9eS",
+  "user_code": "WDJB-MJHT",
+  "verification_uri": "https://gitlab.example.com/oauth/device",
+  "verification_uri_complete": "https://gitlab.example.com/oauth/device?user_code=WDJB-MJHT",
+  "expires_in": 300,
+  "interval": 0
+}"#;
+
+    const PENDING: &str = r#"{"error":"authorization_pending","error_description":"The authorization request is still pending"}"#;
+
+    #[test]
+    fn device_flow_waits_for_the_authorization() {
+        let server = MockServer::start(&[
+            ("POST /oauth/authorize_device", 200, AUTHORIZATION),
+            ("POST /oauth/token", 400, PENDING),
+            ("POST /oauth/token", 400, PENDING),
+            (
+                "POST /oauth/token",
+                200,
+                r#"{"access_token":"secret","token_type":"Bearer","expires_in":7200}"#,
+            ),
+        ]);
+        let gitlab = GitLab::new(&server.host);
+
+        let authorization = gitlab.authorize_device("app", "api").unwrap();
+        assert_eq!(authorization.user_code, "WDJB-MJHT");
+        let token = gitlab.poll_device_token("app", &authorization).unwrap();
+        assert_eq!(token.access_token, "secret");
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 4);
+        assert_eq!(requests[0][3], "client_id=app&scope=api");
+        assert!(requests[3][3].contains("device_code=GmRhmhcxhwAzkoEqiMEg_DnyEysNkuNhszIySk9eS"));
+    }
+
+    #[test]
+    fn device_flow_reports_the_errors() {
+        let authorization: DeviceAuthorization = serde_json::from_str(AUTHORIZATION).unwrap();
+        let poll = |status, body| {
+            let server = MockServer::start(&[("POST /oauth/token", status, body)]);
+            GitLab::new(&server.host).poll_device_token("app", &authorization)
+        };
+
+        assert!(matches!(
+            poll(400, r#"{"error":"access_denied"}"#),
+            Err(GitLabError::Denied)
+        ));
+        assert!(matches!(
+            poll(400, r#"{"error":"expired_token"}"#),
+            Err(GitLabError::Expired)
+        ));
+        match poll(
+            401,
+            r#"{"error":"invalid_client","error_description":"Client authentication failed"}"#,
+        ) {
+            Err(GitLabError::Status(401, message)) => {
+                assert_eq!(message, "Client authentication failed")
+            }
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+
+    #[test]
+    fn requests_are_authenticated() {
+        let server = MockServer::start(&[
+            (
+                "GET /api/v4/user",
+                200,
+                r#"{"id":1,"username":"ada","name":"Ada Lovelace","state":"active"}"#,
+            ),
+            ("DELETE /api/v4/personal_access_tokens/self", 204, ""),
+        ]);
+        let gitlab = GitLab::new(&server.host).with_token("secret");
+
+        assert_eq!(gitlab.user().unwrap().username, "ada");
+        gitlab.revoke_personal_access_token().unwrap();
+        let requests = server.requests();
+        assert_eq!(
+            requests[1][..2],
+            ["DELETE", "/api/v4/personal_access_tokens/self"]
+        );
+        assert!(requests.iter().all(|request| request[2] == "Bearer secret"));
+
+        match GitLab::new(&server.host).project("group/missing") {
+            Err(GitLabError::Status(404, message)) => assert_eq!(message, "404 Not Found"),
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+
+    /// A fork of `exercise` whose repository is being copied
+    fn fork(status: &str) -> String {
+        format!(
+            r#"{{"id":42,"name":"exercise","path_with_namespace":"ada/exercise","description":null,
+                "http_url_to_repo":"https://gitlab.example.com/ada/exercise.git",
+                "import_status":"{status}","import_error":{}}}"#,
+            if status == "failed" {
+                r#""Repository could not be copied""#
+            } else {
+                "null"
+            }
+        )
+    }
+
+    #[test]
+    fn waits_for_the_fork() {
+        let (started, finished) = (fork("started"), fork("finished"));
+        let server = MockServer::start(&[("GET /api/v4/projects/42", 200, &finished)]);
+        let gitlab = GitLab::new(&server.host);
+        let started: Project = serde_json::from_str(&started).unwrap();
+
+        let fork = gitlab
+            .wait_for_fork(started.clone(), Duration::from_secs(60))
+            .unwrap();
+        assert_eq!(fork.import_status.as_deref(), Some("finished"));
+
+        match gitlab.wait_for_fork(started, Duration::ZERO) {
+            Err(GitLabError::ForkTimeout(path, 0)) => assert_eq!(path, "ada/exercise"),
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+
+    #[test]
+    fn reports_failed_forks() {
+        let failed = fork("failed");
+        let server = MockServer::start(&[("GET /api/v4/projects/42", 200, &failed)]);
+        let started: Project = serde_json::from_str(&fork("scheduled")).unwrap();
+
+        match GitLab::new(&server.host).wait_for_fork(started, Duration::from_secs(60)) {
+            Err(GitLabError::ForkFailed(_, error)) => {
+                assert_eq!(error, "Repository could not be copied")
+            }
+            result => panic!("unexpected {result:?}"),
+        }
+    }
+
+    #[test]
+    fn project_path_reads_remotes() {
+        for remote in [
+            "https://gitlab.com/group/exercise.git",
+            "git@gitlab.com:group/exercise.git",
+            "ssh://git@gitlab.com/group/exercise",
+        ] {
+            assert_eq!(project_path(remote).as_deref(), Some("group/exercise"));
+        }
+    }
+}
@@ -1,9 +1,13 @@
-use colored::Colorize;
-use indent::indent_all_by;
+use crate::report::{Failure, PhaseError, Status, TestOutcome};
 use serde::{Deserialize, Serialize};
 use std::path::Path;
+use std::time::Duration;
+
+/// Prefix of the names of the tests googletest does not run
+const DISABLED_PREFIX: &str = "DISABLED_";
 
 #[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
 pub enum TestError {
     #[error("unable to read test file: {0}")]
     IoError(#[from] std::io::Error),
@@ -11,11 +15,26 @@ pub enum TestError {
     #[error("unable to parse json: {0}")]
     DeserializationError(#[from] serde_json::Error),
 
-    #[error("program crashed during test")]
+    #[error("unable to parse the results: {0}")]
+    Format(String),
+
+    #[error("program crashed during test: {0}")]
     ExecutionError(String),
 
-    #[error("test failed")]
-    TestFailed(UnitTest),
+    #[error("tests timed out after {0}s")]
+    Timeout(u64),
+
+    #[error("unable to list the tests: {0}")]
+    ListTests(String),
+
+    #[error("no results written to {0}, the Makefile must write them to its RESULTS directory")]
+    MissingResults(String),
+}
+
+impl PhaseError for TestError {
+    fn is_timeout(&self) -> bool {
+        matches!(self, TestError::Timeout(_))
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
@@ -26,7 +45,9 @@ pub struct UnitTest {
     pub disabled: u32,
     pub errors: u32,
     pub timestamp: String,
-    pub time: String,
+
+    #[serde(with = "time")]
+    pub time: Duration,
     pub testsuites: Vec<TestCase>,
 }
 
@@ -37,7 +58,9 @@ pub struct TestCase {
     pub failures: u32,
     pub disabled: u32,
     pub errors: u32,
-    pub time: String,
+
+    #[serde(with = "time")]
+    pub time: Duration,
     pub testsuite: Vec<TestInfo>,
 }
 
@@ -47,13 +70,19 @@ pub struct TestInfo {
     pub file: String,
     pub line: u32,
     pub status: TestStatus,
-    pub result: String,
+    pub result: TestResult,
     pub timestamp: String,
-    pub time: String,
+
+    #[serde(with = "time")]
+    pub time: Duration,
     pub classname: String,
 
     #[serde(default)]
     pub failures: Vec<TestFailure>,
+
+    /// The messages of `GTEST_SKIP()`, written by the recent versions of googletest only
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<TestSkipped>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
@@ -64,6 +93,11 @@ pub struct TestFailure {
     pub kind: String,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+pub struct TestSkipped {
+    pub message: String,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
 pub enum TestStatus {
     #[default]
@@ -74,167 +108,355 @@ pub enum TestStatus {
     NotRun,
 }
 
-impl TryFrom<TestCase> for UnitTest {
-    type Error = TestError;
+/// How a test ended, `COMPLETED` even when it failed
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Default)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TestResult {
+    #[default]
+    Completed,
+
+    /// Skipped with `GTEST_SKIP()`
+    Skipped,
+
+    /// Disabled, and not run
+    Suppressed,
+}
+
+impl UnitTest {
+    pub fn try_from_file(path: &Path) -> Result<UnitTest, TestError> {
+        let file = std::fs::read_to_string(path).map_err(TestError::from)?;
+        Ok(serde_json::from_str(&file)?)
+    }
+
+    /// The tests of the report, in the model shared by every test framework
+    pub fn outcomes(&self) -> Vec<TestOutcome> {
+        self.testsuites
+            .iter()
+            .flat_map(|suite| &suite.testsuite)
+            .map(TestInfo::outcome)
+            .collect()
+    }
+}
 
-    fn try_from(test: TestCase) -> Result<UnitTest, Self::Error> {
-        let test = UnitTest {
-            testsuites: vec![test.clone()],
-            name: test.name,
-            tests: test.tests,
-            failures: test.failures,
-            disabled: test.disabled,
-            errors: test.errors,
-            timestamp: "".to_owned(),
-            time: test.time,
+impl TestInfo {
+    fn outcome(&self) -> TestOutcome {
+        let (status, reason) = if !self.failures.is_empty() {
+            (Status::Fail, None)
+        } else if self.result == TestResult::Skipped {
+            let reason = self
+                .skipped
+                .iter()
+                .map(|skipped| skipped.message.trim())
+                .find(|message| !message.is_empty())
+                .map(|message| message.to_string());
+            (Status::Skip, reason)
+        } else if self.status == TestStatus::NotRun || self.result == TestResult::Suppressed {
+            (Status::Disabled, self.disabled_reason())
+        } else {
+            (Status::Pass, None)
         };
 
-        if test.errors > 0 {
-            Err(TestError::ExecutionError("".to_string()))
-        } else if test.failures > 0 {
-            Err(TestError::TestFailed(test))
+        TestOutcome {
+            suite: self.classname.clone(),
+            name: self.name.clone(),
+            status,
+            duration: Some(self.time),
+            file: (!self.file.is_empty()).then(|| self.file.clone()),
+            line: (!self.file.is_empty()).then_some(self.line),
+            failures: self.failures.iter().map(TestFailure::failure).collect(),
+            reason,
+        }
+    }
+
+    /// googletest disables the tests and the suites whose name starts with `DISABLED_`
+    fn disabled_reason(&self) -> Option<String> {
+        if self.name.starts_with(DISABLED_PREFIX) {
+            Some("test disabled".to_string())
+        } else if self.classname.starts_with(DISABLED_PREFIX) {
+            Some(format!("suite {} disabled", self.classname))
         } else {
-            Ok(test)
+            None
         }
     }
 }
 
-impl UnitTest {
-    pub fn add_suite(&mut self, suite: TestCase) {
-        self.tests += suite.tests;
-        self.failures += suite.failures;
-        self.disabled += suite.disabled;
-        self.errors += suite.errors;
-        self.testsuites.push(suite);
+impl TestFailure {
+    pub fn message_and_location(&self) -> (Option<String>, String) {
+        let (location, message) = self
+            .failure
+            .split_once('\n')
+            .map(|(a, b)| (Some(a.to_string()), b.to_string()))
+            .unwrap_or_else(|| (None, self.failure.clone()));
+
+        (location.to_owned(), message.to_owned())
     }
 
-    pub fn has_failed(&self) -> bool {
-        self.failures > 0 || self.errors > 0
+    /// The `file:line` googletest prefixes the failure message with
+    pub fn source_location(&self) -> Option<(String, u32)> {
+        let (location, _) = self.message_and_location();
+        let location = location?;
+        let (file, line) = location.rsplit_once(':')?;
+        Some((file.to_string(), line.trim().parse().ok()?))
     }
 
-    pub fn try_from_file(path: &Path) -> Result<UnitTest, TestError> {
-        let file = std::fs::read_to_string(path).map_err(TestError::from)?;
-        let test: UnitTest = serde_json::from_str(&file).map_err(TestError::from)?;
-        if test.has_failed() {
-            Err(TestError::TestFailed(test))
-        } else {
-            Ok(test)
+    fn failure(&self) -> Failure {
+        let (location, message) = self.message_and_location();
+        match self.source_location() {
+            Some((file, line)) => Failure {
+                message,
+                file: Some(file),
+                line: Some(line),
+            },
+            None => Failure {
+                message,
+                file: location,
+                line: None,
+            },
         }
     }
+}
 
-    pub fn pretty_print(&self) {
-        println!("ran {} tests", self.tests);
-        for test_suite in &self.testsuites {
-            for test in &test_suite.testsuite {
-                if test.failures.is_empty() {
-                    println!("test {}.{} ... {}", test.classname, test.name, "ok".green());
-                } else {
-                    println!(
-                        "test {}.{} ... {}",
-                        test.classname,
-                        test.name,
-                        "FAILED".red()
-                    );
-                }
+/// Durations as written by googletest, e.g. `0.012s`
+mod time {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(time: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&format!("{:.3}s", time.as_secs_f64()))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        let time = String::deserialize(deserializer)?;
+        time.strip_suffix('s')
+            .and_then(|seconds| seconds.parse().ok())
+            .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+            .ok_or_else(|| serde::de::Error::custom(format!("invalid duration '{time}'")))
+    }
+}
+
+/// The tests of googletest's console output, as printed while they run. Unlike the JSON report
+/// it survives a crash: the test started last and never ended is the one that crashed.
+pub fn parse_output(output: &str) -> Vec<TestOutcome> {
+    let mut outcomes = Vec::new();
+    let mut running: Option<(String, Vec<&str>)> = None;
+    for line in output.lines() {
+        if let Some(name) = line.strip_prefix("[ RUN      ] ") {
+            running = Some((name.trim().to_string(), Vec::new()));
+            continue;
+        }
+
+        let end = [
+            ("[       OK ] ", Status::Pass),
+            ("[  FAILED  ] ", Status::Fail),
+            ("[  SKIPPED ] ", Status::Skip),
+        ]
+        .into_iter()
+        .find_map(|(marker, status)| Some((line.strip_prefix(marker)?, status)));
+        match (end, &mut running) {
+            (Some((rest, status)), Some((name, _))) if rest.starts_with(name.as_str()) => {
+                let (name, lines) = running.take().unwrap();
+                let time = rest
+                    .rsplit_once(" (")
+                    .and_then(|(_, time)| time.strip_suffix(" ms)")?.parse().ok())
+                    .map(Duration::from_millis);
+                outcomes.push(console_outcome(&name, status, time, &lines));
             }
+            (_, Some((_, lines))) => lines.push(line),
+            (_, None) => {}
         }
-        println!();
-
-        if self.failures > 0 {
-            println!("failures:");
-            for test_suite in &self.testsuites {
-                for test in &test_suite.testsuite {
-                    if !test.failures.is_empty() {
-                        test.print_details();
-                        println!();
-                    }
+    }
+
+    if let Some((name, lines)) = running {
+        outcomes.push(console_outcome(&name, Status::Crash, None, &lines));
+    }
+    outcomes
+}
+
+/// A test of the console output, from the lines printed while it ran
+fn console_outcome(
+    name: &str,
+    status: Status,
+    time: Option<Duration>,
+    lines: &[&str],
+) -> TestOutcome {
+    let (suite, name) = name.split_once('.').unwrap_or(("", name));
+
+    // Each failure starts with a `file:line: Failure` line, and skips with `file:line: Skipped`
+    let mut failures: Vec<Failure> = Vec::new();
+    let mut reason = None;
+    for line in lines {
+        let header = [": Failure", ": Skipped"]
+            .into_iter()
+            .find_map(|suffix| line.strip_suffix(suffix));
+        let location = header.and_then(|location| {
+            let (file, line) = location.rsplit_once(':')?;
+            Some((file.to_string(), line.parse().ok()?))
+        });
+        match (location, line.ends_with(": Skipped")) {
+            (Some(_), true) => reason = Some(String::new()),
+            (Some((file, line)), false) => failures.push(Failure {
+                message: String::new(),
+                file: Some(file),
+                line: Some(line),
+            }),
+            (None, _) if reason.is_some() => {
+                let reason = reason.as_mut().unwrap();
+                reason.push_str(line);
+                reason.push('\n');
+            }
+            (None, _) => {
+                if let Some(failure) = failures.last_mut() {
+                    failure.message.push_str(line);
+                    failure.message.push('\n');
                 }
             }
-
-            println!();
         }
+    }
+    for failure in &mut failures {
+        failure.message = failure.message.trim_end().to_string();
+    }
 
-        println!(
-            "test result: {}. {} passed; {} failed; {} ignored; finished in {}",
-            if self.failures > 0 {
-                "FAILED".red()
-            } else {
-                "ok".green()
-            },
-            self.tests - self.failures,
-            self.failures,
-            self.disabled,
-            self.time
-        );
+    TestOutcome {
+        suite: suite.to_string(),
+        name: name.to_string(),
+        status,
+        duration: time,
+        file: None,
+        line: None,
+        failures,
+        reason: reason
+            .map(|reason| reason.trim().to_string())
+            .filter(|reason| !reason.is_empty()),
     }
 }
 
-impl TestInfo {
-    pub fn print_details(&self) {
-        println!(
-            "    {} {}",
-            format!("{}.{}", self.classname, self.name).cyan(),
-            format!(
-                "({})",
-                if self.file.is_empty() {
-                    "see logs for details".to_string()
-                } else {
-                    format!("{}:{}", self.file, self.line)
+/// The full names of the tests printed by `--gtest_list_tests`, without the comments giving the
+/// parameters of parameterized tests
+pub fn parse_test_list(output: &str) -> Vec<String> {
+    let mut tests = Vec::new();
+    let mut suite = None;
+    for line in output.lines() {
+        let name = line.split("  #").next().unwrap_or_default().trim_end();
+        match name.strip_prefix("  ") {
+            Some(test) => {
+                if let Some(suite) = &suite {
+                    tests.push(format!("{suite}{}", test.trim()));
                 }
-            )
-            .bright_black()
-            .italic()
+            }
+            // Suites end with a dot, unlike the lines printed before them
+            None => suite = name.ends_with('.').then(|| name.to_string()),
+        }
+    }
+    tests
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Console output of googletest 1.14, the last test crashing
+    const OUTPUT: &str = "\
+Running main() from /usr/src/googletest/googletest/src/gtest_main.cc
+[==========] Running 4 tests from 1 test suite.
+[----------] Global test environment set-up.
+[----------] 4 tests from Add
+[ RUN      ] Add.Positive
+[       OK ] Add.Positive (0 ms)
+[ RUN      ] Add.Negative
+/app/tests/tests.cpp:10: Failure
+Expected equality of these values:
+  add(-1, -2)
+    Which is: -1
+  -3
+
+/app/tests/tests.cpp:11: Failure
+Expected: (add(-1, 0)) < (0), actual: 1 vs 0
+
+[  FAILED  ] Add.Negative (1 ms)
+[ RUN      ] Add.Later
+/app/tests/tests.cpp:20: Skipped
+not yet
+
+[  SKIPPED ] Add.Later (0 ms)
+[ RUN      ] Add.Overflow
+AddressSanitizer:DEADLYSIGNAL
+=================================================================
+==12==ERROR: AddressSanitizer: SEGV on unknown address 0x000000000000
+";
+
+    /// Output of `--gtest_list_tests`, with typed and value-parameterized tests
+    const LIST: &str = "\
+Running main() from /usr/src/googletest/googletest/src/gtest_main.cc
+Add.
+  Positive
+  DISABLED_Overflow
+Typed/0.  # TypeParam = int
+  Adds
+Values/Param.
+  Square/0  # GetParam() = 1
+  Square/1  # GetParam() = 2
+";
+
+    #[test]
+    fn parses_the_console_output() {
+        let outcomes = parse_output(OUTPUT);
+        let statuses = outcomes.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [Status::Pass, Status::Fail, Status::Skip, Status::Crash]
         );
+        assert_eq!(outcomes[0].suite, "Add");
+        assert_eq!(outcomes[0].name, "Positive");
+        assert_eq!(outcomes[1].duration, Some(Duration::from_millis(1)));
+        assert_eq!(outcomes[3].duration, None);
+        assert_eq!(outcomes[2].reason.as_deref(), Some("not yet"));
+    }
 
-        let printed_failures = self.failures.iter().take(1);
-        for failure in printed_failures {
-            let (location, message) = failure.message_and_location();
-            println!(
-                "{} {}",
-                indent_all_by(4, message),
-                location
-                    .map(|s| format!("({s})").bright_black().italic())
-                    .unwrap_or_default(),
-            );
-        }
+    #[test]
+    fn parses_the_console_failures() {
+        let failures = &parse_output(OUTPUT)[1].failures;
+        assert_eq!(failures.len(), 2);
+        assert_eq!(
+            failures[0].message,
+            "Expected equality of these values:\n  add(-1, -2)\n    Which is: -1\n  -3"
+        );
+        assert_eq!(failures[0].file.as_deref(), Some("/app/tests/tests.cpp"));
+        assert_eq!(failures[1].line, Some(11));
+    }
 
-        if self.failures.len() > 1 {
-            println!(
-                "{}",
-                format!("    ... and {} more", self.failures.len() - 1).magenta()
-            );
-        }
+    #[test]
+    fn parses_the_test_list() {
+        assert_eq!(
+            parse_test_list(LIST),
+            [
+                "Add.Positive",
+                "Add.DISABLED_Overflow",
+                "Typed/0.Adds",
+                "Values/Param.Square/0",
+                "Values/Param.Square/1",
+            ]
+        );
+        assert!(parse_test_list("").is_empty());
     }
-}
 
-impl TestFailure {
-    pub fn message_and_location(&self) -> (Option<String>, String) {
-        let (location, message) = self
-            .failure
-            .split_once('\n')
-            .map(|(a, b)| (Some(a.to_string()), b.to_string()))
-            .unwrap_or_else(|| (None, self.failure.clone()));
+    #[test]
+    fn failures_start_with_their_location() {
+        let failure = TestFailure {
+            failure: "/app/tests/tests.cpp:10\nExpected: (a) < (b), actual: 5 vs 3\n".to_string(),
+            kind: String::new(),
+        };
+        assert_eq!(
+            failure.source_location(),
+            Some(("/app/tests/tests.cpp".to_string(), 10))
+        );
 
-        // if self.failure.contains("Expected equality of these values:") {
-        //     let mut data = message.splitn(4, '\n');
-        //     data.next();
-        //     let var = data.next().unwrap().trim();
-        //     let val = data
-        //         .next()
-        //         .unwrap()
-        //         .split_once("Which is: ")
-        //         .unwrap()
-        //         .1
-        //         .trim();
-        //     let expected = data.next().unwrap().trim();
-        //     let message = format!(
-        //         "Expected: {} == {}, actual: {} vs {}",
-        //         var, expected, val, expected
-        //     );
-
-        //     (location.to_owned(), message)
-        // } else {
-        (location.to_owned(), message.to_owned())
-        // }
+        let failure = TestFailure {
+            failure: "unknown file\nC++ exception with description \"boom\" thrown in the test \
+                      body."
+                .to_string(),
+            kind: String::new(),
+        };
+        assert_eq!(failure.source_location(), None);
+        assert_eq!(failure.failure().file.as_deref(), Some("unknown file"));
     }
 }
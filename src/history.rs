@@ -0,0 +1,201 @@
+//! The history of the local runs of an exercise, kept in its state directory to tell which tests
+//! changed outcome since the previous run.
+
+use crate::report::{MemoryFinding, RunReport, Status, TestOutcome};
+use crate::workspace::{self, STATE_DIR};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// File of the state directory with a run per line, the oldest first
+pub const HISTORY_FILE: &str = "history.jsonl";
+
+/// Summary of a local run of the tests
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunRecord {
+    /// When the run ended, in seconds since the Unix epoch
+    pub timestamp: u64,
+
+    /// Commit checked out, `None` outside of a git repository
+    pub commit: Option<String>,
+
+    /// Whether the working tree had uncommitted changes, i.e. differed from `commit`
+    #[serde(default)]
+    pub dirty: bool,
+
+    pub tests: Vec<TestRecord>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub memory: Vec<MemoryFinding>,
+}
+
+/// How a test ended in a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TestRecord {
+    /// Full name of the test, e.g. `Suite.name`
+    pub name: String,
+    pub status: Status,
+
+    #[serde(default, with = "crate::report::optional_seconds")]
+    pub duration: Option<Duration>,
+}
+
+/// How the outcome of a test changed since the previous run
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Change {
+    /// Passed in the previous run, failed in this one
+    Regressed,
+
+    /// Failed in the previous run, passed in this one
+    Fixed,
+}
+
+impl RunRecord {
+    /// The summary of `report`, ending now
+    pub fn new(report: &RunReport, commit: Option<String>, dirty: bool) -> Self {
+        Self {
+            timestamp: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_secs(),
+            commit,
+            dirty,
+            tests: report
+                .tests
+                .iter()
+                .map(|test| TestRecord {
+                    name: test.full_name(),
+                    status: test.status,
+                    duration: test.duration,
+                })
+                .collect(),
+            memory: report.memory.clone(),
+        }
+    }
+
+    pub fn time(&self) -> SystemTime {
+        UNIX_EPOCH + Duration::from_secs(self.timestamp)
+    }
+
+    pub fn count(&self, status: Status) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| test.status == status)
+            .count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(Status::Pass)
+    }
+
+    pub fn failed(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| test.status.is_failure())
+            .count()
+    }
+
+    /// How `test` changed since this run, tests that were skipped or not run being left out
+    pub fn change(&self, test: &TestOutcome) -> Option<Change> {
+        let name = test.full_name();
+        let previous = self.tests.iter().find(|record| record.name == name)?.status;
+        match (previous, test.status) {
+            (Status::Pass, status) if status.is_failure() => Some(Change::Regressed),
+            (previous, Status::Pass) if previous.is_failure() => Some(Change::Fixed),
+            _ => None,
+        }
+    }
+}
+
+/// Every run recorded in the workspace at `root`, the oldest first. Lines that cannot be read,
+/// e.g. written by another version, are left out.
+pub fn load(root: &Path) -> Vec<RunRecord> {
+    std::fs::read_to_string(root.join(STATE_DIR).join(HISTORY_FILE))
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// The last run recorded in the workspace at `root`
+pub fn last(root: &Path) -> Option<RunRecord> {
+    load(root).pop()
+}
+
+/// Append `run` to the history of the workspace at `root`
+pub fn record(root: &Path, run: &RunRecord) -> std::io::Result<()> {
+    let path = workspace::state_dir(root)?.join(HISTORY_FILE);
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    writeln!(file, "{}", serde_json::to_string(run)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outcome(name: &str, status: Status) -> TestOutcome {
+        TestOutcome {
+            suite: "Stack".to_string(),
+            name: name.to_string(),
+            status,
+            duration: Some(Duration::from_millis(3)),
+            file: None,
+            line: None,
+            failures: Vec::new(),
+            reason: None,
+        }
+    }
+
+    fn run(tests: Vec<TestOutcome>) -> RunRecord {
+        let report = RunReport {
+            tests,
+            ..RunReport::default()
+        };
+        RunRecord::new(&report, Some("3ed1a64".to_string()), false)
+    }
+
+    #[test]
+    fn changes_between_runs() {
+        let previous = run(vec![
+            outcome("Push", Status::Pass),
+            outcome("Pop", Status::Fail),
+            outcome("Peek", Status::Pass),
+            outcome("Clear", Status::Skip),
+            outcome("Resize", Status::Crash),
+        ]);
+        let change = |name, status| previous.change(&outcome(name, status));
+        assert_eq!(change("Push", Status::Timeout), Some(Change::Regressed));
+        assert_eq!(change("Pop", Status::Pass), Some(Change::Fixed));
+        assert_eq!(change("Resize", Status::Pass), Some(Change::Fixed));
+        assert_eq!(change("Peek", Status::Pass), None);
+        assert_eq!(change("Resize", Status::Fail), None);
+
+        // Tests skipped or new in either run did not change
+        assert_eq!(change("Clear", Status::Fail), None);
+        assert_eq!(change("Peek", Status::Skip), None);
+        assert_eq!(change("Size", Status::Fail), None);
+    }
+
+    #[test]
+    fn records_are_appended() {
+        let root = tempfile::tempdir().unwrap();
+        assert_eq!(last(root.path()), None);
+
+        let first = run(vec![outcome("Push", Status::Fail)]);
+        let second = run(vec![outcome("Push", Status::Pass)]);
+        record(root.path(), &first).unwrap();
+        record(root.path(), &second).unwrap();
+
+        // Lines written by other versions are left out
+        let path = root.path().join(STATE_DIR).join(HISTORY_FILE);
+        let mut file = std::fs::OpenOptions::new().append(true).open(path).unwrap();
+        writeln!(file, "{{\"timestamp\": \"yesterday\"}}").unwrap();
+
+        assert_eq!(load(root.path()), [first, second.clone()]);
+        assert_eq!(last(root.path()), Some(second));
+    }
+}
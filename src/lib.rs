@@ -0,0 +1,46 @@
+//! Building, testing and reporting on Dojo exercises, as done by the `neodojo` command.
+//!
+//! - [`pipeline`] builds and tests an exercise in its container.
+//! - [`gunit`], [`sarif`] and [`asan`] read what the container produced: the googletest
+//!   results, the compiler diagnostics and the sanitizer reports, into a [`RunReport`].
+//!   [`framework`] reads the results of the other test frameworks.
+//! - [`render`] writes a [`RunReport`] to the terminal, following the color choice of
+//!   [`colored::control`], as JSON, as JUnit XML or as TAP.
+//! - [`dojo`], [`compose`] and [`workspace`] read the exercise itself.
+//! - [`history`] keeps a summary of the local runs, to find the tests that regressed.
+//!
+//! The result model, [`RunReport`] and the types it is made of, follows semantic versioning:
+//! its types are `#[non_exhaustive]`, fields and variants being added by minor versions and only
+//! removed along with the major version. So are the error types.
+//!
+//! [`RunReport`]: report::RunReport
+
+pub mod asan;
+pub mod assertion;
+pub mod compose;
+pub mod dojo;
+pub mod framework;
+pub mod gunit;
+pub mod history;
+pub mod paths;
+pub mod pipeline;
+pub mod render;
+pub mod report;
+pub mod sarif;
+pub mod workspace;
+
+use codespan_reporting::term::termcolor::ColorChoice;
+
+/// The color choice of the diagnostics, following the one of [`colored`]
+fn color_choice() -> ColorChoice {
+    if colorize() {
+        ColorChoice::Always
+    } else {
+        ColorChoice::Never
+    }
+}
+
+/// Whether to print in color, as set for [`colored`]
+fn colorize() -> bool {
+    colored::control::SHOULD_COLORIZE.should_colorize()
+}
@@ -0,0 +1,51 @@
+use colored::Colorize;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+/// Prints the log records to stderr, leaving stdout to the results
+struct Logger;
+
+static LOGGER: Logger = Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        match record.level() {
+            Level::Error => eprintln!("{}{} {}", "error".red().bold(), ":".bold(), record.args()),
+            Level::Warn => eprintln!(
+                "{}{} {}",
+                "warning".yellow().bold(),
+                ":".bold(),
+                record.args()
+            ),
+            Level::Info => eprintln!("{}", record.args()),
+            Level::Debug | Level::Trace => {
+                eprintln!("{}", record.args().to_string().bright_black())
+            }
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Install the logger, `None` silencing everything
+pub fn init(level: Option<Level>) {
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(level.map_or(LevelFilter::Off, |level| level.to_level_filter()));
+}
+
+/// Whether only the final summary should be printed, with `--quiet`
+pub fn quiet() -> bool {
+    log::max_level() < LevelFilter::Info
+}
+
+/// Whether the steps are detailed as they run, with `--verbose`
+pub fn verbose() -> bool {
+    log::max_level() >= LevelFilter::Debug
+}
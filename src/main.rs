@@ -1,12 +1,15 @@
-mod asan;
 mod cli;
-mod dojo;
-mod gunit;
-mod sarif;
+mod config;
+mod credentials;
+mod git;
+mod gitlab;
+mod logger;
+mod template;
 
 use clap::Parser;
+use std::process::ExitCode;
 
-fn main() {
+fn main() -> ExitCode {
     let cli = cli::Cli::parse();
-    cli.exec();
+    cli.exec().into()
 }
@@ -0,0 +1,256 @@
+use crate::workspace::clean;
+use compose_spec::service::volumes::{self, Mount};
+use compose_spec::{Compose, Identifier};
+use itertools::Itertools;
+use std::cmp::Reverse;
+use std::path::{Component, Path, PathBuf};
+
+/// Maps paths inside the exercise container to files of the workspace on the host
+#[derive(Debug, Clone, Default)]
+pub struct PathMapper {
+    /// Root of the workspace on the host
+    root: PathBuf,
+
+    /// Bind mounts of the service, as (path in the container, path on the host)
+    mounts: Vec<(PathBuf, PathBuf)>,
+
+    /// Directory the host paths are relative to, none for absolute paths
+    base: Option<PathBuf>,
+}
+
+impl PathMapper {
+    /// A mapper of the workspace at `root`, its paths being relative to the current directory
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            mounts: Vec::new(),
+            base: std::env::current_dir().ok(),
+        }
+    }
+
+    /// Make the host paths relative to `base` instead of the current directory
+    pub fn with_base(mut self, base: &Path) -> Self {
+        self.base = Some(base.to_path_buf());
+        self
+    }
+
+    /// Add the bind mounts of `service`, relative host paths being resolved from the workspace root
+    pub fn with_compose(mut self, compose: &Compose, service: &Identifier) -> Self {
+        let Some(service) = compose.services.get(service) else {
+            return self;
+        };
+
+        for mount in volumes::into_long_iter(service.volumes.clone()) {
+            if let Mount::Bind(bind) = mount {
+                self.mounts.push((
+                    bind.common.target.as_path().to_path_buf(),
+                    self.root.join(bind.source.as_path()),
+                ));
+            }
+        }
+
+        // Nested mounts take precedence over their parents
+        self.mounts
+            .sort_by_key(|(container, _)| Reverse(container.components().count()));
+        self
+    }
+
+    /// Map a path inside the container to the corresponding file of the workspace, relative to
+    /// the base directory unless they only share the root of the filesystem. Returns `None` if
+    /// the file is not part of the workspace.
+    pub fn to_host(&self, path: &Path) -> Option<PathBuf> {
+        let mounted = self
+            .mounts
+            .iter()
+            .find_map(|(container, host)| Some(host.join(path.strip_prefix(container).ok()?)));
+        if let Some(mounted) = mounted.filter(|path| path.is_file()) {
+            return Some(self.relative(&clean(&mounted)));
+        }
+
+        // Files copied into the image instead of being mounted can only be found by looking
+        // for the longest suffix of the path that exists in the workspace, as long as the path
+        // is where the workspace is in the container and not e.g. a system header
+        if !self.in_workspace(path) {
+            return None;
+        }
+        let components = path.components().collect_vec();
+        (0..components.len())
+            .map(|i| components[i..].iter().collect::<PathBuf>())
+            .filter(|path| path.is_relative())
+            .map(|path| self.root.join(path))
+            .find(|path| path.is_file())
+            .map(|path| self.relative(&clean(&path)))
+    }
+
+    /// Whether `path` is within a bind mount or the directory it is mounted in, e.g. `/app` for
+    /// `/app/src`, where the workspace is copied by the image
+    fn in_workspace(&self, path: &Path) -> bool {
+        self.mounts.iter().any(|(container, _)| {
+            let parent = container
+                .parent()
+                .filter(|parent| parent.components().count() > 1);
+            path.starts_with(parent.unwrap_or(container))
+        })
+    }
+
+    /// `path` relative to the base directory, unless they only share the root of the filesystem
+    fn relative(&self, path: &Path) -> PathBuf {
+        match &self.base {
+            Some(base) if path.is_absolute() => relative_to(path, base),
+            _ => path.to_path_buf(),
+        }
+    }
+
+    /// The host path of `path` for display, or `path` itself if it is not part of the workspace
+    pub fn display(&self, path: &str) -> String {
+        self.to_host(Path::new(path))
+            .map(|path| path.display().to_string())
+            .unwrap_or_else(|| path.to_string())
+    }
+
+    /// Map every absolute path of `text`, optionally followed by `:line:column`, to the host. The
+    /// paths may be quoted or wrapped in parentheses or brackets, e.g. `(/app/src/ex0.c:12)`.
+    pub fn rewrite(&self, text: &str) -> String {
+        let rewritten = text
+            .lines()
+            .map(|line| {
+                line.split(' ')
+                    .map(|word| self.rewrite_word(word))
+                    .join(" ")
+            })
+            .join("\n");
+
+        if text.ends_with('\n') {
+            rewritten + "\n"
+        } else {
+            rewritten
+        }
+    }
+
+    fn rewrite_word(&self, word: &str) -> String {
+        let start = word.len() - word.trim_start_matches(OPENING).len();
+        if !word[start..].starts_with('/') {
+            return word.to_string();
+        }
+        let end = word[start..]
+            .find(CLOSING)
+            .map_or(word.len(), |end| start + end);
+
+        let path = match word[start..end].split_once(':') {
+            Some((path, position)) => format!("{}:{position}", self.display(path)),
+            None => self.display(&word[start..end]),
+        };
+        format!("{}{path}{}", &word[..start], &word[end..])
+    }
+}
+
+/// Characters found before a path in the messages of the tools
+const OPENING: &[char] = &['(', '[', '{', '<', '"', '\'', '`'];
+
+/// Characters found after a path, and never within one in practice
+const CLOSING: &[char] = &[')', ']', '}', '>', '"', '\'', '`', ',', ';'];
+
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(path, base)| path == base)
+        .count();
+    if common <= 1 {
+        return path.to_path_buf();
+    }
+
+    let mut relative = base
+        .components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .collect::<PathBuf>();
+    relative.extend(path.components().skip(common));
+    if relative.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        relative
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A workspace with `src/ex0.c`, copied to `/app`, and `tests/` mounted at `/app/tests`
+    fn workspace() -> (tempfile::TempDir, PathMapper) {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("src")).unwrap();
+        std::fs::create_dir(dir.path().join("tests")).unwrap();
+        std::fs::write(dir.path().join("src/ex0.c"), "int answer(void);\n").unwrap();
+        std::fs::write(dir.path().join("tests/tests.cpp"), "#include \"ex0.h\"\n").unwrap();
+        let mut mapper = PathMapper::new(dir.path()).with_base(Path::new("/"));
+        mapper
+            .mounts
+            .push((PathBuf::from("/app/tests"), dir.path().join("tests")));
+        (dir, mapper)
+    }
+
+    #[test]
+    fn to_host_finds_copied_files() {
+        let (dir, mapper) = workspace();
+        let expected = dir.path().join("src/ex0.c");
+        assert_eq!(mapper.to_host(Path::new("/app/src/ex0.c")), Some(expected));
+        assert_eq!(
+            mapper.to_host(Path::new("/app/tests/tests.cpp")),
+            Some(dir.path().join("tests/tests.cpp"))
+        );
+        assert_eq!(mapper.to_host(Path::new("/usr/include/stdio.h")), None);
+
+        // Only the paths where the workspace is in the container are looked for
+        assert_eq!(mapper.to_host(Path::new("/usr/src/ex0.c")), None);
+    }
+
+    #[test]
+    fn to_host_is_relative_to_the_base() {
+        let (dir, mapper) = workspace();
+        let mapper = mapper.with_base(&dir.path().join("tests"));
+        assert_eq!(
+            mapper.to_host(Path::new("/app/src/ex0.c")),
+            Some(PathBuf::from("../src/ex0.c"))
+        );
+    }
+
+    #[test]
+    fn rewrite_handles_delimiters() {
+        let (dir, mapper) = workspace();
+        let host = dir.path().join("src/ex0.c").display().to_string();
+
+        assert_eq!(
+            mapper.rewrite("    #0 0x55 in answer /app/src/ex0.c:5\n"),
+            format!("    #0 0x55 in answer {host}:5\n")
+        );
+        assert_eq!(
+            mapper.rewrite("Failure (/app/src/ex0.c:12)"),
+            format!("Failure ({host}:12)")
+        );
+        assert_eq!(
+            mapper.rewrite("in '/app/src/ex0.c', [/app/src/ex0.c:3:7]"),
+            format!("in '{host}', [{host}:3:7]")
+        );
+        assert_eq!(
+            mapper.rewrite("(/usr/lib/libc.so.6+0x29d8f)"),
+            "(/usr/lib/libc.so.6+0x29d8f)"
+        );
+    }
+
+    #[test]
+    fn relative_to_walks_up() {
+        let relative = |path, base| relative_to(Path::new(path), Path::new(base));
+        assert_eq!(relative("/ws/src/ex0.c", "/ws"), PathBuf::from("src/ex0.c"));
+        assert_eq!(
+            relative("/ws/src/ex0.c", "/ws/tests"),
+            PathBuf::from("../src/ex0.c")
+        );
+        assert_eq!(relative("/ws", "/ws"), PathBuf::from("."));
+        assert_eq!(
+            relative("/ws/src/ex0.c", "/home"),
+            PathBuf::from("/ws/src/ex0.c")
+        );
+    }
+}
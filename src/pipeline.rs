@@ -0,0 +1,884 @@
+//! Building and testing an exercise in its container.
+//!
+//! ```no_run
+//! use neodojo::pipeline::Pipeline;
+//! use std::time::Duration;
+//!
+//! let pipeline = Pipeline::new("path/to/exercise").test_timeout(Some(Duration::from_secs(60)));
+//! match pipeline.run() {
+//!     Ok(report) => println!("{} tests passed", report.passed()),
+//!     Err(err) => eprintln!("{err}"),
+//! }
+//! ```
+//!
+//! [`Pipeline::run`] runs every step at once, [`Pipeline::prepare`] gives access to each of them
+//! to report progress or show the build warnings.
+
+use crate::asan::Asan;
+use crate::compose::{ComposeError, Environment, COMPOSE_FILE};
+use crate::dojo::{DojoAssignment, DojoAssignmentError, ASSIGNMENT_FILE};
+use crate::framework::{self, TestFramework};
+use crate::gunit::{self, TestError};
+use crate::paths::PathMapper;
+use crate::report::{
+    Diagnostic, Failure, PhaseError, PhaseKind, RunReport, Severity, Status, TestOutcome,
+};
+use crate::sarif;
+use indent::indent_all_by;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use serde_sarif::sarif::Sarif;
+use std::collections::HashSet;
+use std::io::{ErrorKind, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread::{JoinHandle, ScopedJoinHandle};
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+
+/// Subdirectory of the results directory where the tests run in isolation write their results
+const ISOLATED_DIR: &str = "isolated";
+
+/// Exit status of `timeout` when the command it runs timed out
+const TIMED_OUT: i32 = 124;
+
+/// Time left to `timeout` in the container to kill the command before the compose client is
+/// killed instead
+const KILL_GRACE: Duration = Duration::from_secs(5);
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum PipelineError {
+    #[error("invalid dojo workspace: {0}")]
+    DojoWorkspace(PathBuf),
+
+    #[error("invalid dojo assignment: {0}")]
+    Assignment(DojoAssignmentError),
+
+    #[error("invalid exercise environment: {0}")]
+    Compose(#[from] ComposeError),
+
+    #[error("unable to create the results directory: {0}")]
+    ResultsDirectory(std::io::Error),
+
+    #[error("unable to run the container runtime: {0}")]
+    Runtime(std::io::Error),
+
+    #[error("error building the project")]
+    Build(#[from] BuildError),
+
+    #[error("error running tests: {0}")]
+    Test(#[from] TestError),
+}
+
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum BuildError {
+    #[error("incorrect makefile format: {0}")]
+    IncompatibleMakefile(String),
+
+    #[error("build failed")]
+    BuildFailed(Vec<Diagnostic>),
+
+    #[error("build timed out after {0}s")]
+    Timeout(u64),
+
+    #[error("unable to run the container runtime: {0}")]
+    Runtime(std::io::Error),
+}
+
+/// Why a command run in the container did not complete
+#[derive(Debug, thiserror::Error)]
+enum RunError {
+    #[error("{0}")]
+    Io(#[from] std::io::Error),
+
+    /// Killed once its timeout elapsed, along with what it printed until then
+    #[error("timed out")]
+    Timeout(Output),
+}
+
+impl From<RunError> for std::io::Error {
+    fn from(err: RunError) -> Self {
+        match err {
+            RunError::Io(err) => err,
+            RunError::Timeout(_) => ErrorKind::TimedOut.into(),
+        }
+    }
+}
+
+impl PhaseError for PipelineError {
+    fn is_timeout(&self) -> bool {
+        match self {
+            PipelineError::Build(err) => err.is_timeout(),
+            PipelineError::Test(err) => err.is_timeout(),
+            _ => false,
+        }
+    }
+}
+
+impl PhaseError for BuildError {
+    fn is_timeout(&self) -> bool {
+        matches!(self, BuildError::Timeout(_))
+    }
+}
+
+/// Names of the files the test suite writes to the result volume
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResultFiles {
+    /// googletest JSON report
+    pub tests: String,
+
+    /// Sanitizer report
+    pub memory: String,
+}
+
+impl Default for ResultFiles {
+    fn default() -> Self {
+        Self {
+            tests: "test_detail.json".to_string(),
+            memory: "memory.txt".to_string(),
+        }
+    }
+}
+
+/// How an exercise is built and tested, see the [module documentation](self)
+#[derive(Debug, Clone)]
+pub struct Pipeline {
+    root: PathBuf,
+    runtime: String,
+    build_timeout: Option<Duration>,
+    test_timeout: Option<Duration>,
+    files: ResultFiles,
+    isolate: Option<usize>,
+}
+
+impl Pipeline {
+    /// The pipeline of the exercise at `root`, run with docker and without time limits
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            runtime: "docker".to_string(),
+            build_timeout: None,
+            test_timeout: None,
+            files: ResultFiles::default(),
+            isolate: None,
+        }
+    }
+
+    /// Container runtime running the exercise, `docker` or a compatible one like `podman`
+    pub fn runtime(mut self, runtime: impl Into<String>) -> Self {
+        self.runtime = runtime.into();
+        self
+    }
+
+    pub fn build_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.build_timeout = timeout;
+        self
+    }
+
+    pub fn test_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.test_timeout = timeout;
+        self
+    }
+
+    pub fn result_files(mut self, files: ResultFiles) -> Self {
+        self.files = files;
+        self
+    }
+
+    /// Run each test in a process of its own, `jobs` at a time, instead of the whole test suite
+    /// at once. A test crashing or timing out, the test timeout applying to each test, no longer
+    /// hides the results of the others.
+    pub fn isolate(mut self, jobs: Option<usize>) -> Self {
+        self.isolate = jobs;
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Load and validate the workspace, before anything is run in the container
+    pub fn prepare(&self) -> Result<Exercise<'_>, PipelineError> {
+        let assignment = DojoAssignment::try_from_file(&self.root.join(ASSIGNMENT_FILE)).map_err(
+            |err| match err {
+                DojoAssignmentError::Io(_) => PipelineError::DojoWorkspace(self.root.clone()),
+                err => PipelineError::Assignment(err),
+            },
+        )?;
+        let environment = Environment::load(&self.root.join(COMPOSE_FILE), &assignment.result)?;
+
+        let results = tempfile::tempdir().map_err(PipelineError::ResultsDirectory)?;
+        let overrides = environment.write_override(results.path())?;
+
+        Ok(Exercise {
+            pipeline: self,
+            paths: environment.path_mapper(),
+            assignment,
+            environment,
+            results,
+            overrides,
+        })
+    }
+
+    /// Run every step, the report telling whether the tests passed
+    pub fn run(&self) -> Result<RunReport, PipelineError> {
+        let exercise = self.prepare()?;
+        let mut report = RunReport::default();
+        report.phase(PhaseKind::Setup, || exercise.start())?;
+        report.phase(PhaseKind::Clean, || exercise.clean())?;
+        report.diagnostics = report.phase(PhaseKind::Build, || exercise.build())?;
+        let results = report.phase(PhaseKind::Test, || exercise.test())?;
+        report.merge(results);
+        Ok(report)
+    }
+}
+
+/// A validated exercise, whose steps are to be run in order
+#[derive(Debug)]
+pub struct Exercise<'a> {
+    pipeline: &'a Pipeline,
+    assignment: DojoAssignment,
+    environment: Environment,
+    paths: PathMapper,
+
+    /// Directory mounted as the result volume, removed along with the exercise but for the files
+    /// of the container the user cannot remove
+    results: TempDir,
+
+    /// Compose file keeping the service idle, see [`Environment::write_override`]
+    overrides: PathBuf,
+}
+
+impl Exercise<'_> {
+    pub fn pipeline(&self) -> &Pipeline {
+        self.pipeline
+    }
+
+    pub fn assignment(&self) -> &DojoAssignment {
+        &self.assignment
+    }
+
+    pub fn environment(&self) -> &Environment {
+        &self.environment
+    }
+
+    /// Mapping of the paths of the container to the ones of the workspace
+    pub fn paths(&self) -> &PathMapper {
+        &self.paths
+    }
+
+    /// Directory the test suite writes its results to
+    pub fn results_dir(&self) -> &Path {
+        self.results.path()
+    }
+
+    /// Start the container of the exercise
+    pub fn start(&self) -> Result<(), PipelineError> {
+        output(
+            &mut self.command(&["run", "-d", "--rm", "--build"], &[]),
+            None,
+        )
+        .map(|_| ())
+        .map_err(|err| PipelineError::Runtime(err.into()))
+    }
+
+    /// Remove the previous build
+    pub fn clean(&self) -> Result<(), PipelineError> {
+        output(
+            &mut self.command(&["exec"], &["make", "-C", "src", "clean", "-s"]),
+            None,
+        )
+        .map(|_| ())
+        .map_err(|err| PipelineError::Runtime(err.into()))
+    }
+
+    /// Build the test suite, returning its warnings
+    pub fn build(&self) -> Result<Vec<Diagnostic>, BuildError> {
+        let timeout = self.pipeline.build_timeout;
+        let makefile = std::fs::read_to_string(self.pipeline.root.join("src/Makefile"))
+            .map_err(|_| BuildError::IncompatibleMakefile("unable to read Makefile".to_string()))?;
+
+        let (_, cflags) = makefile
+            .lines()
+            .find(|line| line.starts_with("CFLAGS:="))
+            .ok_or(BuildError::IncompatibleMakefile(
+                "missing CFLAGS".to_string(),
+            ))?
+            .split_once('=')
+            .ok_or(BuildError::IncompatibleMakefile(
+                "missing CFLAGS".to_string(),
+            ))?;
+
+        let cflags = format!("CFLAGS={cflags} -fdiagnostics-format=sarif-stderr");
+        let build = self
+            .exec(&[], &["make", "-C", "src", "tests", &cflags], timeout)
+            .map_err(|err| match err {
+                RunError::Timeout(_) => BuildError::Timeout(timeout.unwrap_or_default().as_secs()),
+                RunError::Io(err) => BuildError::Runtime(err),
+            })?;
+
+        let mut diagnostics = Vec::new();
+        for report in String::from_utf8_lossy(&build.stderr).lines() {
+            let sarif: Sarif = match serde_json::from_str(report) {
+                Ok(sarif) => sarif,
+                Err(_) => continue,
+            };
+
+            diagnostics.extend(sarif::diagnostics(&sarif, &self.paths));
+        }
+
+        if diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+        {
+            Err(BuildError::BuildFailed(diagnostics))
+        } else {
+            Ok(diagnostics)
+        }
+    }
+
+    /// Run the test suite and read its results, the ones of the tests run before a crash or a hang
+    /// being recovered from the output of the test suite
+    pub fn test(&self) -> Result<RunReport, TestError> {
+        if let Some(jobs) = self.pipeline.isolate {
+            return self.test_isolated(jobs);
+        }
+
+        let timeout = self.pipeline.test_timeout;
+        let asan = || Asan::from_file(&self.results.path().join(&self.pipeline.files.memory));
+        let output = match self.exec(&[], &["make", "-C", "src", "run_tests"], timeout) {
+            Ok(output) => output,
+            Err(RunError::Timeout(output)) => {
+                // The tests that ended are kept, the one that hung is timed out
+                let timed_out = || TestError::Timeout(timeout.unwrap_or_default().as_secs());
+                let mut report =
+                    recover_results(&printed(&output), &asan()).ok_or_else(timed_out)?;
+                let running = report
+                    .tests
+                    .last_mut()
+                    .filter(|test| test.status == Status::Crash)
+                    .ok_or_else(timed_out)?;
+                running.status = Status::Timeout;
+                running.reason = None;
+                return Ok(report);
+            }
+            Err(RunError::Io(err)) => return Err(TestError::IoError(err)),
+        };
+
+        match read_results(
+            self.results.path(),
+            &self.pipeline.files,
+            self.assignment.test_framework,
+        ) {
+            Err(err @ (TestError::IoError(_) | TestError::ExecutionError(_))) => {
+                recover_results(&printed(&output), &asan()).ok_or(err)
+            }
+            result => result,
+        }
+    }
+
+    /// The full names of the tests of the test suite
+    pub fn list_tests(&self) -> Result<Vec<String>, TestError> {
+        let (dir, target) = self.isolated_dir("list")?;
+        let output = self
+            .exec(
+                &["-e", "GTEST_LIST_TESTS=1"],
+                &["make", "-s", "-C", "src", "run_tests", &target],
+                self.pipeline.test_timeout,
+            )
+            .map_err(|err| TestError::ListTests(err.to_string()))?;
+        let _ = std::fs::remove_dir_all(dir);
+
+        let tests = gunit::parse_test_list(&String::from_utf8_lossy(&output.stdout));
+        if tests.is_empty() {
+            return Err(TestError::ListTests("no test found".to_string()));
+        }
+        Ok(tests)
+    }
+
+    /// Run each test in a process of its own, `jobs` at a time
+    fn test_isolated(&self, jobs: usize) -> Result<RunReport, TestError> {
+        let tests = self.list_tests()?;
+        let next = AtomicUsize::new(0);
+        let mut reports = std::thread::scope(|scope| {
+            let workers = (0..jobs.clamp(1, tests.len()))
+                .map(|_| {
+                    scope.spawn(|| {
+                        let mut reports = Vec::new();
+                        loop {
+                            let index = next.fetch_add(1, Ordering::Relaxed);
+                            let Some(test) = tests.get(index) else {
+                                return reports;
+                            };
+                            reports.push((index, self.test_alone(index, test)));
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            join_workers(workers, &tests)
+        });
+        reports.sort_by_key(|(index, _)| *index);
+
+        let mut merged = RunReport::default();
+        for (_, report) in reports {
+            merged.merge(report?);
+        }
+        Ok(merged)
+    }
+
+    /// Run the single test `test`, its sanitizer findings being attributed to it
+    fn test_alone(&self, index: usize, test: &str) -> Result<RunReport, TestError> {
+        let (dir, target) = self.isolated_dir(&index.to_string())?;
+        let timeout = self.pipeline.test_timeout;
+        let filter = format!("GTEST_FILTER={test}");
+        let output = self.exec(
+            &["-e", &filter],
+            &["make", "-s", "-C", "src", "run_tests", &target],
+            timeout,
+        );
+        let (suite, name) = test.split_once('.').unwrap_or(("", test));
+        let outcome = |status, reason: Option<String>, failures| TestOutcome {
+            suite: suite.to_string(),
+            name: name.to_string(),
+            status,
+            duration: timeout.filter(|_| status == Status::Timeout),
+            file: None,
+            line: None,
+            failures,
+            reason,
+        };
+
+        let output = match output {
+            Ok(output) => output,
+            Err(RunError::Timeout(_)) => {
+                return Ok(RunReport {
+                    tests: vec![outcome(Status::Timeout, None, Vec::new())],
+                    ..RunReport::default()
+                })
+            }
+            Err(RunError::Io(err)) => return Err(TestError::IoError(err)),
+        };
+
+        let asan = Asan::from_file(&dir.join(&self.pipeline.files.memory));
+        let mut report =
+            match read_results(&dir, &self.pipeline.files, self.assignment.test_framework) {
+                Ok(report) => report,
+                Err(_) => {
+                    let output = printed(&output);
+                    // Without a crash, nothing at all written means `RESULTS` was not used
+                    let empty =
+                        std::fs::read_dir(&dir).map_or(true, |mut dir| dir.next().is_none());
+                    let crashed = gunit::parse_output(&output)
+                        .last()
+                        .is_none_or(|test| test.status == Status::Crash);
+                    if empty && signal(&output).is_none() && !crashed {
+                        let target = target.trim_start_matches("RESULTS=");
+                        return Err(TestError::MissingResults(target.to_string()));
+                    }
+
+                    // The test may crash before googletest even prints its name
+                    recover_results(&output, &asan).unwrap_or_else(|| {
+                        let reason = asan.signal().or_else(|| signal(&output).map(String::from));
+                        let failures = asan
+                            .crash_report()
+                            .map(|report| Failure {
+                                message: report.to_string(),
+                                file: None,
+                                line: None,
+                            })
+                            .into_iter()
+                            .collect();
+                        RunReport {
+                            tests: vec![outcome(Status::Crash, reason, failures)],
+                            memory: asan.findings.clone(),
+                            ..RunReport::default()
+                        }
+                    })
+                }
+            };
+
+        for finding in &mut report.memory {
+            finding.test = Some(test.to_string());
+        }
+        Ok(report)
+    }
+
+    /// A directory of its own in the results directory, and the `RESULTS` variable of the
+    /// Makefile pointing to it inside the container
+    fn isolated_dir(&self, name: &str) -> Result<(PathBuf, String), TestError> {
+        let dir = self.results.path().join(ISOLATED_DIR).join(name);
+        std::fs::create_dir_all(&dir)?;
+        let target = self
+            .environment
+            .results_target()
+            .unwrap_or_default()
+            .join(ISOLATED_DIR)
+            .join(name);
+        Ok((dir, format!("RESULTS={}", target.display())))
+    }
+
+    /// Execute `args` in the service, killed there once `timeout` is elapsed: killing the
+    /// compose client would leave it running
+    fn exec(
+        &self,
+        options: &[&str],
+        args: &[&str],
+        timeout: Option<Duration>,
+    ) -> Result<Output, RunError> {
+        let mut action = vec!["exec"];
+        action.extend(options);
+        let Some(timeout) = timeout else {
+            return output(&mut self.command(&action, args), None);
+        };
+
+        let seconds = timeout.as_secs_f64().to_string();
+        let mut within = vec!["timeout", "-k", "1", &seconds];
+        within.extend(args);
+        let output = output(
+            &mut self.command(&action, &within),
+            Some(timeout + KILL_GRACE),
+        )?;
+        if output.status.code() == Some(TIMED_OUT) {
+            return Err(RunError::Timeout(output));
+        }
+        Ok(output)
+    }
+
+    /// The compose `action` on the service, e.g. `exec` with the command to execute as `args`
+    fn command(&self, action: &[&str], args: &[&str]) -> Command {
+        let mut command = Command::new(&self.pipeline.runtime);
+        command
+            .args(["compose", "--file", COMPOSE_FILE, "--file"])
+            .arg(&self.overrides)
+            .args(action)
+            .arg(self.environment.service.as_str())
+            .args(args)
+            .current_dir(&self.pipeline.root);
+        command
+    }
+}
+
+/// Read the results written by the test suite to the `results` directory, the test framework
+/// being detected when not given
+pub fn read_results(
+    results: &Path,
+    files: &ResultFiles,
+    framework: Option<TestFramework>,
+) -> Result<RunReport, TestError> {
+    let asan = Asan::from_file(&results.join(&files.memory));
+    let tests = match framework::read_tests(&results.join(&files.tests), framework) {
+        Ok(tests) => tests,
+        // A crash leaves no googletest report behind
+        Err(_) if asan.crashed => {
+            return Err(TestError::ExecutionError(
+                asan.findings
+                    .first()
+                    .map(|finding| format!("{}: {}", finding.sanitizer, finding.message))
+                    .unwrap_or_else(|| "no results written".to_string()),
+            ))
+        }
+        Err(_) if !asan.findings.is_empty() => Vec::new(),
+        Err(err) => return Err(err),
+    };
+
+    Ok(RunReport {
+        tests,
+        memory: asan.findings,
+        ..RunReport::default()
+    })
+}
+
+/// The results of the tests run, from what the test suite printed, for when it wrote none,
+/// e.g. because it crashed. `None` when no test even started.
+pub fn recover_results(output: &str, asan: &Asan) -> Option<RunReport> {
+    let mut tests = gunit::parse_output(output);
+    let Some(crashed) = tests.last_mut().filter(|test| test.status == Status::Crash) else {
+        return (!tests.is_empty()).then(|| RunReport {
+            tests,
+            memory: asan.findings.clone(),
+            ..RunReport::default()
+        });
+    };
+    crashed.reason = asan
+        .signal()
+        .or_else(|| signal(output).map(|signal| signal.to_string()))
+        .or_else(|| {
+            asan.findings
+                .first()
+                .map(|finding| finding.sanitizer.clone())
+        });
+    if let Some(report) = asan.crash_report() {
+        crashed.failures.push(Failure {
+            message: report.to_string(),
+            file: None,
+            line: None,
+        });
+    }
+
+    Some(RunReport {
+        tests,
+        memory: asan.findings.clone(),
+        ..RunReport::default()
+    })
+}
+
+/// The report of a test run in isolation, along with its index in the list of the tests
+type IsolatedReport = (usize, Result<RunReport, TestError>);
+
+/// The reports of the tests run by `workers`, the tests of a worker that panicked, which are lost
+/// along with it, being crashed
+fn join_workers(
+    workers: Vec<ScopedJoinHandle<'_, Vec<IsolatedReport>>>,
+    tests: &[String],
+) -> Vec<IsolatedReport> {
+    let mut reports = Vec::new();
+    let mut panic = None;
+    for worker in workers {
+        match worker.join() {
+            Ok(done) => reports.extend(done),
+            Err(payload) => panic = Some(panic_message(payload.as_ref())),
+        }
+    }
+
+    if let Some(panic) = panic {
+        let done = reports
+            .iter()
+            .map(|(index, _)| *index)
+            .collect::<HashSet<_>>();
+        for (index, test) in tests.iter().enumerate() {
+            if !done.contains(&index) {
+                let (suite, name) = test.split_once('.').unwrap_or(("", test));
+                let outcome = TestOutcome {
+                    suite: suite.to_string(),
+                    name: name.to_string(),
+                    status: Status::Crash,
+                    duration: None,
+                    file: None,
+                    line: None,
+                    failures: Vec::new(),
+                    reason: Some(format!("neodojo panicked while running it: {panic}")),
+                };
+                let report = RunReport {
+                    tests: vec![outcome],
+                    ..RunReport::default()
+                };
+                reports.push((index, Ok(report)));
+            }
+        }
+    }
+    reports
+}
+
+/// The message a thread panicked with
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown error".to_string())
+}
+
+/// What a command printed, its standard output then its standard error
+fn printed(output: &Output) -> String {
+    format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stdout),
+        String::from_utf8_lossy(&output.stderr)
+    )
+}
+
+/// The signal that killed the test suite, as reported by the shell running it
+fn signal(output: &str) -> Option<&'static str> {
+    // The exit status of the shell is 128 plus the number of the signal
+    const SIGNALS: [(&str, &str, &str); 6] = [
+        ("SIGSEGV", "Segmentation fault", "Error 139"),
+        ("SIGABRT", "Aborted", "Error 134"),
+        ("SIGFPE", "Floating point exception", "Error 136"),
+        ("SIGBUS", "Bus error", "Error 135"),
+        ("SIGILL", "Illegal instruction", "Error 132"),
+        ("SIGKILL", "Killed", "Error 137"),
+    ];
+    SIGNALS
+        .iter()
+        .find(|(_, message, status)| output.contains(message) || output.contains(status))
+        .map(|(signal, _, _)| *signal)
+}
+
+/// Run `command` to completion, killing it once `timeout` is elapsed, and log it along with
+/// what it printed
+fn output(command: &mut Command, timeout: Option<Duration>) -> Result<Output, RunError> {
+    log::debug!(
+        "$ {}",
+        std::iter::once(command.get_program())
+            .chain(command.get_args())
+            .map(|arg| arg.to_string_lossy())
+            .join(" ")
+    );
+
+    let start = Instant::now();
+    let result = match timeout {
+        Some(timeout) => output_within(command, timeout),
+        None => command.output().map_err(RunError::from),
+    };
+
+    let output = match &result {
+        Ok(output) => {
+            log::debug!("  {} in {:.2?}", output.status, start.elapsed());
+            output
+        }
+        Err(RunError::Timeout(output)) => {
+            log::debug!("  timed out after {:.2?}", start.elapsed());
+            output
+        }
+        Err(err) => {
+            log::debug!("  failed after {:.2?}: {err}", start.elapsed());
+            return result;
+        }
+    };
+    for (name, content) in [("stdout", &output.stdout), ("stderr", &output.stderr)] {
+        let content = String::from_utf8_lossy(content);
+        if !content.trim().is_empty() {
+            log::trace!("  {name}:\n{}", indent_all_by(4, content.trim_end()));
+        }
+    }
+    result
+}
+
+/// Run `command`, the compose client, killing it once `timeout` is elapsed
+fn output_within(command: &mut Command, timeout: Duration) -> Result<Output, RunError> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    // The pipes are drained in the background, the child would block once they are full. A
+    // killed client may leave them open to the processes it started, what was read so far is kept.
+    let drain = |pipe: Option<Box<dyn Read + Send>>| {
+        let content = Arc::new(Mutex::new(Vec::new()));
+        let read = Arc::clone(&content);
+        let reader = std::thread::spawn(move || {
+            let Some(mut pipe) = pipe else {
+                return;
+            };
+            let mut buffer = [0; 4096];
+            while let Ok(count @ 1..) = pipe.read(&mut buffer) {
+                read.lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .extend_from_slice(&buffer[..count]);
+            }
+        });
+        (reader, content)
+    };
+    let stdout = drain(child.stdout.take().map(|pipe| Box::new(pipe) as _));
+    let stderr = drain(child.stderr.take().map(|pipe| Box::new(pipe) as _));
+
+    let start = Instant::now();
+    let (status, timed_out) = loop {
+        if let Some(status) = child.try_wait()? {
+            break (status, false);
+        }
+        if start.elapsed() >= timeout {
+            child.kill()?;
+            break (child.wait()?, true);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    };
+
+    let collect = |(reader, content): (JoinHandle<()>, Arc<Mutex<Vec<u8>>>)| {
+        let deadline = Instant::now() + KILL_GRACE;
+        while timed_out && !reader.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        if !timed_out || reader.is_finished() {
+            let _ = reader.join();
+        }
+        std::mem::take(&mut *content.lock().unwrap_or_else(PoisonError::into_inner))
+    };
+    let output = Output {
+        status,
+        stdout: collect(stdout),
+        stderr: collect(stderr),
+    };
+    if timed_out {
+        return Err(RunError::Timeout(output));
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// googletest console output of a test suite whose second test crashed
+    const CRASHED: &str = "\
+Running main() from gtest_main.cc
+[==========] Running 2 tests from 1 test suite.
+[----------] 2 tests from Stack
+[ RUN      ] Stack.Push
+[       OK ] Stack.Push (0 ms)
+[ RUN      ] Stack.Pop
+make: *** [Makefile:23: run_tests] Segmentation fault (core dumped)
+";
+
+    #[test]
+    fn recovers_the_crashed_test() {
+        let report = recover_results(CRASHED, &Asan::default()).unwrap();
+        let statuses = report.tests.iter().map(|test| test.status).collect_vec();
+        assert_eq!(statuses, [Status::Pass, Status::Crash]);
+        assert_eq!(report.tests[1].reason.as_deref(), Some("SIGSEGV"));
+    }
+
+    #[test]
+    fn recovers_the_tests_that_ended() {
+        let (ended, _) = CRASHED.split_once("[ RUN      ] Stack.Pop").unwrap();
+        let report = recover_results(ended, &Asan::default()).unwrap();
+        assert_eq!(report.tests.len(), 1);
+        assert_eq!(report.tests[0].status, Status::Pass);
+
+        assert!(recover_results("Running main() from gtest_main.cc", &Asan::default()).is_none());
+    }
+
+    #[test]
+    fn tests_of_panicked_workers_crash() {
+        let tests = ["S.A", "S.B", "S.C"].map(String::from);
+        let reports = std::thread::scope(|scope| {
+            let workers = vec![
+                scope.spawn(|| vec![(1, Ok(RunReport::default()))]),
+                scope.spawn(|| panic!("unexpected output")),
+            ];
+            join_workers(workers, &tests)
+        });
+
+        let crashed = reports
+            .iter()
+            .filter_map(|(index, report)| Some((*index, report.as_ref().ok()?.tests.first()?)))
+            .collect_vec();
+        assert_eq!(crashed.len(), 2);
+        assert_eq!(crashed[0].0, 0);
+        assert_eq!(crashed[1].1.full_name(), "S.C");
+        assert_eq!(crashed[1].1.status, Status::Crash);
+        assert_eq!(
+            crashed[1].1.reason.as_deref(),
+            Some("neodojo panicked while running it: unexpected output")
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeouts_keep_the_output() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo started; echo failing >&2; exec sleep 10"]);
+        let Err(RunError::Timeout(output)) = output(&mut command, Some(Duration::from_millis(300)))
+        else {
+            panic!("the command runs longer than its timeout");
+        };
+        assert_eq!(output.stdout, b"started\n");
+        assert_eq!(output.stderr, b"failing\n");
+    }
+}
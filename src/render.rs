@@ -0,0 +1,983 @@
+//! Writers of a [`RunReport`]: to the terminal, as JSON, as JUnit XML and as TAP.
+
+use crate::assertion::Assertion;
+use crate::history::{Change, RunRecord};
+use crate::paths::PathMapper;
+use crate::report::{
+    Diagnostic, Failure, PhaseKind, PhaseStatus, RunReport, Severity, Span, Status, TestOutcome,
+};
+use codespan_reporting::diagnostic::{self, Label};
+use codespan_reporting::files::{Files, SimpleFiles};
+use codespan_reporting::term::termcolor::StandardStream;
+use colored::{ColoredString, Colorize};
+use indent::indent_all_by;
+use itertools::Itertools;
+use serde_yaml::Mapping;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::path::Path;
+use std::time::Duration;
+
+/// Options controlling how test results are printed
+#[derive(Debug, Clone, Default)]
+pub struct PrintOptions {
+    /// Maps the paths reported from inside the container to the sources of the workspace
+    pub paths: PathMapper,
+
+    /// Print every failure of a test instead of only the first one
+    pub all_failures: bool,
+
+    /// The previous run, to flag the tests that regressed or were fixed since
+    pub previous: Option<RunRecord>,
+
+    /// Number of the slowest tests to print, along with the time spent in each phase
+    pub slowest: Option<usize>,
+
+    /// Duration above which a test is reported as slow, set by the assignment
+    pub slow_threshold: Option<Duration>,
+}
+
+impl RunReport {
+    pub fn pretty_print(&self, options: &PrintOptions) {
+        println!("ran {} tests", self.tests.len());
+        for test in &self.tests {
+            let change = match options.previous.as_ref().and_then(|run| run.change(test)) {
+                Some(Change::Regressed) => {
+                    format!(" {}", "regressed since last run".red().italic())
+                }
+                Some(Change::Fixed) => format!(" {}", "fixed since last run".green().italic()),
+                None => String::new(),
+            };
+            match &test.reason {
+                Some(reason) if test.status.is_failure() => println!(
+                    "test {} ... {}{change}",
+                    test.full_name(),
+                    format!("{} ({reason})", test.status.colored()).red()
+                ),
+                Some(reason) => println!(
+                    "test {} ... {} {}{change}",
+                    test.full_name(),
+                    test.status.colored(),
+                    format!("({reason})").yellow()
+                ),
+                None => println!(
+                    "test {} ... {}{change}",
+                    test.full_name(),
+                    test.status.colored()
+                ),
+            }
+        }
+        println!();
+
+        if self.failed() > 0 {
+            println!("failures:");
+            for test in self.tests.iter().filter(|test| test.status.is_failure()) {
+                test.print_details(options);
+                println!();
+            }
+
+            println!();
+        }
+
+        if !self.memory.is_empty() {
+            println!("memory errors:");
+            for finding in &self.memory {
+                println!(
+                    "    {}{} {} {}",
+                    finding.sanitizer.cyan(),
+                    ":".bold(),
+                    finding.message,
+                    finding
+                        .test
+                        .as_ref()
+                        .map(|test| format!("(in {test})").bright_black().italic())
+                        .unwrap_or_default()
+                );
+            }
+            println!();
+        }
+
+        if let Some(threshold) = options.slow_threshold {
+            let slow = self.slower_than(threshold);
+            for test in &slow {
+                println!(
+                    "{}{} test {} took {:.2}s, more than the {:.2}s threshold of the assignment",
+                    "warning".yellow().bold(),
+                    ":".bold(),
+                    test.full_name(),
+                    test.duration.unwrap_or_default().as_secs_f64(),
+                    threshold.as_secs_f64()
+                );
+            }
+            if !slow.is_empty() {
+                println!();
+            }
+        }
+
+        if let Some(count) = options.slowest {
+            self.print_timing(count);
+            println!();
+        }
+
+        self.print_summary();
+    }
+
+    /// Print the `count` slowest tests and the time spent in each phase of the run
+    pub fn print_timing(&self, count: usize) {
+        let slowest = self.slowest(count);
+        if !slowest.is_empty() {
+            println!("slowest tests:");
+            for test in slowest {
+                println!(
+                    "    {:>8.3}s  {}",
+                    test.duration.unwrap_or_default().as_secs_f64(),
+                    test.full_name()
+                );
+            }
+            println!();
+        }
+
+        if !self.phases.is_empty() {
+            println!("time spent:");
+            for phase in &self.phases {
+                let kind = match phase.kind {
+                    PhaseKind::Setup => "setup",
+                    PhaseKind::Clean => "clean",
+                    PhaseKind::Build => "build",
+                    PhaseKind::Test => "test",
+                };
+                let status = match phase.status {
+                    PhaseStatus::Success => String::new(),
+                    PhaseStatus::Failure => format!(" {}", "failed".red()),
+                    PhaseStatus::Timeout => format!(" {}", "timed out".red()),
+                };
+                println!("    {:>8.3}s  {kind}{status}", phase.duration.as_secs_f64());
+            }
+        }
+    }
+
+    /// The final line of the results, the only one printed with `--quiet`
+    pub fn print_summary(&self) {
+        let memory = match self.memory.len() {
+            0 => String::new(),
+            1 => "1 memory error; ".to_string(),
+            count => format!("{count} memory errors; "),
+        };
+        println!(
+            "test result: {}. {} passed; {} failed; {} skipped; {} ignored; {memory}finished in {:.2}s",
+            if self.has_failed() {
+                "FAILED".red()
+            } else {
+                "ok".green()
+            },
+            self.passed(),
+            self.failed(),
+            self.count(Status::Skip),
+            self.count(Status::Disabled),
+            self.duration().as_secs_f64()
+        );
+    }
+
+    /// Print the diagnostics of the compiler, the most severe last
+    pub fn print_diagnostics(&self) {
+        let writer = StandardStream::stdout(crate::color_choice());
+        let config = codespan_reporting::term::Config::default();
+        for diagnostic in self.diagnostics.iter().sorted_by_key(|d| d.severity) {
+            let mut files = SimpleFiles::new();
+            let rendered = diagnostic.to_codespan(&mut files);
+            codespan_reporting::term::emit(&mut writer.lock(), &config, &files, &rendered).unwrap();
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// The report as JUnit XML, the errors of the compiler and of the sanitizers being test
+    /// suites of their own
+    pub fn to_junit(&self) -> String {
+        // Tests of a suite are not always next to each other, e.g. in a merged report
+        let mut by_suite: BTreeMap<&str, Vec<&TestOutcome>> = BTreeMap::new();
+        for test in &self.tests {
+            by_suite.entry(&test.suite).or_default().push(test);
+        }
+
+        let mut suites: Vec<(String, Vec<String>, [usize; 4])> = Vec::new();
+        for (suite, tests) in by_suite {
+            let counts = [
+                tests.len(),
+                tests.iter().filter(|t| t.status == Status::Fail).count(),
+                tests
+                    .iter()
+                    .filter(|t| matches!(t.status, Status::Crash | Status::Timeout))
+                    .count(),
+                tests
+                    .iter()
+                    .filter(|t| matches!(t.status, Status::Skip | Status::Disabled))
+                    .count(),
+            ];
+            let cases = tests.iter().map(|test| test.to_junit()).collect();
+            suites.push((suite.to_string(), cases, counts));
+        }
+
+        let errors = self
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .map(|diagnostic| {
+                let location = diagnostic.location().unwrap_or_default();
+                testcase("build", &location, None, &failure(&diagnostic.message, ""))
+            })
+            .collect::<Vec<_>>();
+        if !errors.is_empty() {
+            suites.push((
+                "build".to_string(),
+                errors.clone(),
+                [errors.len(), errors.len(), 0, 0],
+            ));
+        }
+
+        let findings = self
+            .memory
+            .iter()
+            .map(|finding| {
+                let name = match &finding.test {
+                    Some(test) => format!("{} in {test}", finding.sanitizer),
+                    None => finding.sanitizer.clone(),
+                };
+                testcase("memory", &name, None, &failure(&finding.message, ""))
+            })
+            .collect::<Vec<_>>();
+        if !findings.is_empty() {
+            let count = findings.len();
+            suites.push(("memory".to_string(), findings, [count, count, 0, 0]));
+        }
+
+        let total = suites.iter().fold([0; 4], |mut total, (_, _, counts)| {
+            for (total, count) in total.iter_mut().zip(counts) {
+                *total += count;
+            }
+            total
+        });
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites name=\"neodojo\" tests=\"{}\" \
+             failures=\"{}\" errors=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n",
+            total[0],
+            total[1],
+            total[2],
+            total[3],
+            self.duration().as_secs_f64()
+        );
+        for (name, cases, [tests, failures, errors, skipped]) in suites {
+            xml += &format!(
+                "  <testsuite name=\"{}\" tests=\"{tests}\" failures=\"{failures}\" \
+                 errors=\"{errors}\" skipped=\"{skipped}\">\n",
+                escape(&name)
+            );
+            for case in cases {
+                xml += &case;
+            }
+            xml += "  </testsuite>\n";
+        }
+        xml += "</testsuites>\n";
+        xml
+    }
+
+    /// The report as TAP version 13, the errors of the compiler and of the sanitizers being
+    /// failed test points of their own
+    pub fn to_tap(&self) -> String {
+        let mut points = self
+            .tests
+            .iter()
+            .map(TestOutcome::to_tap)
+            .collect::<Vec<_>>();
+        for diagnostic in &self.diagnostics {
+            if diagnostic.severity == Severity::Error {
+                let mut yaml = Mapping::new();
+                yaml.insert("message".into(), yaml_text(diagnostic.message.clone()));
+                yaml.insert("severity".into(), "fail".into());
+                let name = format!("build {}", diagnostic.location().unwrap_or_default());
+                points.push((false, name.trim().to_string(), None, yaml));
+            }
+        }
+        for finding in &self.memory {
+            let mut yaml = Mapping::new();
+            yaml.insert("message".into(), yaml_text(finding.message.clone()));
+            yaml.insert("severity".into(), "fail".into());
+            if let Some(test) = &finding.test {
+                yaml.insert("test".into(), test.clone().into());
+            }
+            points.push((false, format!("memory {}", finding.sanitizer), None, yaml));
+        }
+
+        let mut tap = format!("TAP version 13\n1..{}\n", points.len());
+        for (number, (ok, description, directive, yaml)) in points.into_iter().enumerate() {
+            tap += &format!(
+                "{}ok {} - {}{}\n",
+                if ok { "" } else { "not " },
+                number + 1,
+                description.replace('#', "\\#"),
+                directive
+                    .map(|directive| format!(" # {directive}"))
+                    .unwrap_or_default()
+            );
+            if !yaml.is_empty() {
+                let yaml = serde_yaml::to_string(&yaml).unwrap_or_default();
+                tap += &format!("  ---\n{}\n  ...\n", indent_all_by(2, yaml.trim_end()));
+            }
+        }
+        tap
+    }
+}
+
+impl Status {
+    /// The outcome as printed next to the name of a test
+    pub fn colored(self) -> ColoredString {
+        match self {
+            Status::Pass => "ok".green(),
+            Status::Fail => "FAILED".red(),
+            Status::Skip => "skipped".yellow(),
+            Status::Disabled => "ignored".yellow(),
+            Status::Crash => "CRASHED".red(),
+            Status::Timeout => "TIMEOUT".red(),
+        }
+    }
+}
+
+impl TestOutcome {
+    pub fn print_details(&self, options: &PrintOptions) {
+        println!(
+            "    {} {}",
+            self.full_name().cyan(),
+            format!(
+                "({})",
+                match (&self.file, self.line) {
+                    (Some(file), Some(line)) => format!("{}:{line}", options.paths.display(file)),
+                    (Some(file), None) => options.paths.display(file),
+                    (None, _) => "see logs for details".to_string(),
+                }
+            )
+            .bright_black()
+            .italic()
+        );
+
+        // The report of a crash has no location, unlike the assertions that failed before it
+        let (reports, failures): (Vec<_>, Vec<_>) = self
+            .failures
+            .iter()
+            .partition(|failure| self.status == Status::Crash && failure.file.is_none());
+
+        // Assertions inside loops tend to fail many times with the exact same message
+        let mut groups: Vec<(&Failure, usize)> = Vec::new();
+        for failure in failures {
+            match groups.iter_mut().find(|(f, _)| f == &failure) {
+                Some((_, count)) => *count += 1,
+                None => groups.push((failure, 1)),
+            }
+        }
+
+        let printed_groups = if options.all_failures {
+            groups.len()
+        } else {
+            1
+        };
+        for (failure, count) in groups.iter().take(printed_groups) {
+            let assertion = failure.assertion();
+
+            let snippet = match (&failure.file, failure.line) {
+                (Some(file), Some(line)) => Some((file.clone(), line)),
+                _ => self.file.clone().zip(self.line),
+            }
+            .and_then(|(file, line)| SourceSnippet::try_new(&options.paths, &file, line));
+
+            let times = if *count > 1 {
+                format!("failed {count} times")
+            } else {
+                "failed".to_string()
+            };
+
+            match snippet {
+                Some(snippet) => {
+                    snippet.emit(
+                        &format!("assertion {times}"),
+                        assertion.as_ref(),
+                        &failure.message,
+                    );
+                    for line in assertion
+                        .map(|a| a.diff(crate::colorize()))
+                        .unwrap_or_default()
+                    {
+                        println!("    {line}");
+                    }
+                }
+                None => {
+                    let message = assertion
+                        .map(|a| a.render(crate::colorize()))
+                        .unwrap_or(failure.message.clone());
+                    let location = match (&failure.file, failure.line) {
+                        (Some(file), Some(line)) => Some(format!("{file}:{line}")),
+                        (file, _) => file.clone(),
+                    };
+                    println!(
+                        "{} {}",
+                        indent_all_by(4, message),
+                        location
+                            .map(|s| format!("({s})").bright_black().italic())
+                            .unwrap_or_default(),
+                    );
+                    if *count > 1 {
+                        println!("{}", format!("    {times}").magenta());
+                    }
+                }
+            }
+        }
+
+        let hidden = groups
+            .iter()
+            .skip(printed_groups)
+            .map(|(_, count)| count)
+            .sum::<usize>();
+        if hidden > 0 {
+            println!(
+                "{}",
+                format!("    ... and {hidden} more (use --all-failures to show them)").magenta()
+            );
+        }
+
+        for report in reports {
+            println!(
+                "{}",
+                indent_all_by(4, options.paths.rewrite(&report.message)).bright_black()
+            );
+        }
+    }
+
+    /// Whether the test point is ok, its description, its directive and its YAML diagnostics
+    fn to_tap(&self) -> (bool, String, Option<String>, Mapping) {
+        let mut yaml = Mapping::new();
+        if let Some(duration) = self.duration {
+            yaml.insert(
+                "duration_ms".into(),
+                (duration.as_secs_f64() * 1000.0).into(),
+            );
+        }
+
+        let directive = match self.status {
+            Status::Pass | Status::Fail => None,
+            // A directive ends with its line, longer reasons going to the diagnostics
+            Status::Skip | Status::Disabled => match self.reason.as_deref().map(str::trim) {
+                Some(reason) if reason.contains('\n') => {
+                    let (first, _) = reason.split_once('\n').unwrap_or_default();
+                    yaml.insert("message".into(), yaml_text(reason.to_string()));
+                    Some(format!("SKIP {}", first.trim()))
+                }
+                Some(reason) => Some(format!("SKIP {reason}")),
+                None => Some("SKIP".to_string()),
+            },
+            Status::Crash | Status::Timeout => {
+                let status = if self.status == Status::Crash {
+                    "crashed"
+                } else {
+                    "timed out"
+                };
+                let message = match &self.reason {
+                    Some(reason) => format!("{status} ({reason})"),
+                    None => status.to_string(),
+                };
+                yaml.insert("message".into(), yaml_text(message));
+                None
+            }
+        };
+
+        if self.status.is_failure() {
+            let failure = self.failures.first();
+            if let Some(failure) = failure.filter(|_| self.status == Status::Fail) {
+                let message = self
+                    .failures
+                    .iter()
+                    .map(|failure| failure.message.as_str())
+                    .join("\n\n");
+                yaml.insert("message".into(), yaml_text(message));
+                if let (Some(file), Some(line)) = (&failure.file, failure.line) {
+                    let mut at = Mapping::new();
+                    at.insert("file".into(), file.clone().into());
+                    at.insert("line".into(), line.into());
+                    yaml.insert("at".into(), at.into());
+                }
+            } else if !self.failures.is_empty() {
+                let details = self
+                    .failures
+                    .iter()
+                    .map(|failure| failure.message.as_str())
+                    .join("\n\n");
+                yaml.insert("details".into(), yaml_text(details));
+            }
+            yaml.insert("severity".into(), "fail".into());
+        }
+
+        let description = self.full_name().lines().join(" ");
+        (!self.status.is_failure(), description, directive, yaml)
+    }
+
+    fn to_junit(&self) -> String {
+        let body = match self.status {
+            Status::Pass => String::new(),
+            Status::Skip | Status::Disabled => match &self.reason {
+                Some(reason) => format!("      <skipped message=\"{}\"/>\n", escape(reason)),
+                None => "      <skipped/>\n".to_string(),
+            },
+            Status::Fail => self
+                .failures
+                .iter()
+                .map(|failure| {
+                    let location = match (&failure.file, failure.line) {
+                        (Some(file), Some(line)) => format!("{file}:{line}\n"),
+                        _ => String::new(),
+                    };
+                    self::failure(&failure.message, &location)
+                })
+                .collect(),
+            Status::Crash | Status::Timeout => {
+                let status = if self.status == Status::Crash {
+                    "crashed"
+                } else {
+                    "timed out"
+                };
+                let message = match &self.reason {
+                    Some(reason) => format!("{status} ({reason})"),
+                    None => status.to_string(),
+                };
+                // The crash report, e.g. of the sanitizer, comes as a failure
+                let report = self
+                    .failures
+                    .iter()
+                    .map(|failure| failure.message.as_str())
+                    .join("\n");
+                error(&message, &report)
+            }
+        };
+        testcase(
+            &self.suite,
+            &self.name,
+            self.duration.map(|d| d.as_secs_f64()),
+            &body,
+        )
+    }
+}
+
+impl Diagnostic {
+    /// `file:line:column`, as far as it is known
+    fn location(&self) -> Option<String> {
+        let file = self.file.as_ref()?;
+        Some(match self.span {
+            Some(span) => format!("{file}:{}:{}", span.line, span.column),
+            None => file.clone(),
+        })
+    }
+
+    /// The diagnostic with a snippet of its file, when it can be read
+    fn to_codespan(
+        &self,
+        files: &mut SimpleFiles<String, String>,
+    ) -> diagnostic::Diagnostic<usize> {
+        let rendered = match self.severity {
+            Severity::Error => diagnostic::Diagnostic::error(),
+            Severity::Warning => diagnostic::Diagnostic::warning(),
+            Severity::Note => diagnostic::Diagnostic::note(),
+        }
+        .with_message(&self.message);
+
+        let source = self
+            .file
+            .as_ref()
+            .and_then(|file| Some((file, std::fs::read_to_string(file).ok()?)));
+        let label = match (source, self.span) {
+            (Some((file, source)), Some(span)) => {
+                let file_id = files.add(file.clone(), source);
+                byte_range(files, file_id, span).map(|range| (file_id, range))
+            }
+            _ => None,
+        };
+        match label {
+            Some((file_id, range)) => rendered.with_labels(vec![
+                Label::primary(file_id, range).with_message(&self.message)
+            ]),
+            None => match self.location() {
+                Some(location) => rendered.with_notes(vec![format!("at {location}")]),
+                None => rendered,
+            },
+        }
+    }
+}
+
+/// The bytes of `span`, up to the end of its line when it has no end, `None` when it is not
+/// within the file
+fn byte_range(
+    files: &SimpleFiles<String, String>,
+    file_id: usize,
+    span: Span,
+) -> Option<Range<usize>> {
+    let offset = |line: u32, column: u32| {
+        files
+            .line_range(file_id, (line as usize).checked_sub(1)?)
+            .ok()?
+            .find(|byte| {
+                files
+                    .location(file_id, *byte)
+                    .is_ok_and(|location| location.column_number == column as usize)
+            })
+    };
+    let start = offset(span.line, span.column)?;
+
+    let end_line = span.end_line.unwrap_or(span.line);
+    let end = match span.end_column {
+        Some(column) => offset(end_line, column),
+        None => (end_line as usize)
+            .checked_sub(1)
+            .and_then(|line| files.line_range(file_id, line).ok())
+            .map(|range| range.start + files.source(file_id).unwrap()[range].trim_end().len()),
+    };
+    Some(start..end.unwrap_or(start).max(start))
+}
+
+/// The line of a source file where an assertion failed
+struct SourceSnippet {
+    files: SimpleFiles<String, String>,
+    file_id: usize,
+    range: Range<usize>,
+}
+
+impl SourceSnippet {
+    fn try_new(paths: &PathMapper, file: &str, line: u32) -> Option<Self> {
+        let path = paths.to_host(Path::new(file))?;
+        let source = std::fs::read_to_string(&path).ok()?;
+        let mut files = SimpleFiles::new();
+        let file_id = files.add(path.display().to_string(), source);
+
+        let range = files
+            .line_range(file_id, line.checked_sub(1)? as usize)
+            .ok()?;
+        let text = &files.get(file_id).ok()?.source()[range.clone()];
+        let start = range.start + (text.len() - text.trim_start().len());
+        let end = range.start + text.trim_end().len();
+
+        Some(Self {
+            files,
+            file_id,
+            range: start..end.max(start),
+        })
+    }
+
+    /// Emit the snippet with the operands of the assertion labelled with their values,
+    /// or with the raw failure message when the assertion could not be parsed
+    fn emit(&self, message: &str, assertion: Option<&Assertion>, failure: &str) {
+        let source = &self.files.get(self.file_id).unwrap().source()[self.range.clone()];
+        let mut labels = vec![Label::primary(self.file_id, self.range.clone())];
+        let mut notes = vec![];
+        match assertion {
+            Some(assertion) => {
+                for (range, label) in assertion.labels(source) {
+                    match range {
+                        Some(range) => labels.push(
+                            Label::secondary(
+                                self.file_id,
+                                self.range.start + range.start..self.range.start + range.end,
+                            )
+                            .with_message(label),
+                        ),
+                        None => notes.push(label),
+                    }
+                }
+            }
+            None => notes.push(failure.to_string()),
+        }
+
+        let writer = StandardStream::stdout(crate::color_choice());
+        let config = codespan_reporting::term::Config::default();
+        let diagnostic = diagnostic::Diagnostic::error()
+            .with_message(message)
+            .with_labels(labels)
+            .with_notes(notes);
+        codespan_reporting::term::emit(&mut writer.lock(), &config, &self.files, &diagnostic)
+            .unwrap();
+    }
+}
+
+fn testcase(classname: &str, name: &str, time: Option<f64>, body: &str) -> String {
+    let time = time
+        .map(|time| format!(" time=\"{time:.3}\""))
+        .unwrap_or_default();
+    let open = format!(
+        "    <testcase classname=\"{}\" name=\"{}\"{time}",
+        escape(classname),
+        escape(name)
+    );
+    if body.is_empty() {
+        format!("{open}/>\n")
+    } else {
+        format!("{open}>\n{body}    </testcase>\n")
+    }
+}
+
+fn failure(message: &str, location: &str) -> String {
+    format!(
+        "      <failure message=\"{}\">{}</failure>\n",
+        escape(message.lines().next().unwrap_or_default()),
+        escape(&format!("{location}{message}"))
+    )
+}
+
+fn error(message: &str, report: &str) -> String {
+    if report.is_empty() {
+        return format!("      <error message=\"{}\"/>\n", escape(message));
+    }
+    format!(
+        "      <error message=\"{}\">{}</error>\n",
+        escape(message),
+        escape(report)
+    )
+}
+
+/// A YAML string, multi-line texts being written as `|` blocks without empty lines, the only ones
+/// the YAMLish reader of `prove` understands
+fn yaml_text(text: String) -> serde_yaml::Value {
+    if !text.contains('\n') {
+        return text.into();
+    }
+    let mut text = text
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .join("\n");
+    text.push('\n');
+    text.into()
+}
+
+/// `text` escaped for XML 1.0, which cannot contain most control characters: the ANSI escape
+/// sequences of colored output are removed, and the other characters replaced by `�`
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\u{1b}' if chars.peek() == Some(&'[') => {
+                // Parameters and intermediate bytes, up to the final byte
+                chars.next();
+                while chars
+                    .next()
+                    .is_some_and(|c| matches!(c, '\u{20}'..='\u{3f}'))
+                {}
+            }
+            '\t' | '\n' | '\r' => escaped.push(c),
+            '\u{0}'..='\u{1f}' | '\u{fffe}' | '\u{ffff}' => escaped.push('\u{fffd}'),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::framework::{Tap, TestFrameworkAdapter};
+    use crate::report::MemoryFinding;
+    use quick_xml::events::Event;
+    use quick_xml::Reader;
+
+    fn outcome(name: &str, status: Status, failures: Vec<Failure>) -> TestOutcome {
+        TestOutcome {
+            suite: "S".to_string(),
+            name: name.to_string(),
+            status,
+            duration: Some(std::time::Duration::from_millis(12)),
+            file: None,
+            line: None,
+            failures,
+            reason: None,
+        }
+    }
+
+    /// Whether `xml` is a well-formed document
+    fn well_formed(xml: &str) -> bool {
+        let mut reader = Reader::from_str(xml);
+        loop {
+            match reader.read_event() {
+                Ok(Event::Eof) => return true,
+                Ok(Event::Text(text)) if text.unescape().is_err() => return false,
+                Ok(_) => {}
+                Err(_) => return false,
+            }
+        }
+    }
+
+    #[test]
+    fn tap_keeps_directives_on_one_line() {
+        let mut skipped = outcome("Skip", Status::Skip, Vec::new());
+        skipped.reason = Some("not on CI\nsee tests/tests.cpp:12".to_string());
+        let report = RunReport {
+            tests: vec![skipped],
+            ..RunReport::default()
+        };
+
+        let tap = report.to_tap();
+        assert!(tap.contains("ok 1 - S.Skip # SKIP not on CI\n"), "{tap}");
+        assert!(tap.contains("    see tests/tests.cpp:12\n"), "{tap}");
+    }
+
+    #[test]
+    fn tap_reads_back() {
+        let report = RunReport {
+            tests: vec![
+                outcome("A", Status::Pass, Vec::new()),
+                outcome(
+                    "B",
+                    Status::Fail,
+                    vec![Failure {
+                        message: "Expected equality of these values:\n  1\n  2".to_string(),
+                        file: Some("tests.cpp".to_string()),
+                        line: Some(3),
+                    }],
+                ),
+                outcome("C", Status::Crash, Vec::new()),
+                outcome("D", Status::Disabled, Vec::new()),
+            ],
+            ..RunReport::default()
+        };
+
+        let read = Tap.parse(&report.to_tap()).unwrap();
+        let statuses = read.iter().map(|test| test.status).collect::<Vec<_>>();
+        assert_eq!(
+            statuses,
+            [Status::Pass, Status::Fail, Status::Fail, Status::Skip]
+        );
+        assert_eq!(read[1].name, "S.B");
+        assert_eq!(read[1].failures[0].line, Some(3));
+        assert_eq!(read[1].duration, Some(std::time::Duration::from_millis(12)));
+    }
+
+    #[test]
+    fn escape_removes_control_characters() {
+        assert_eq!(
+            escape("a < b && \"c\""),
+            "a &lt; b &amp;&amp; &quot;c&quot;"
+        );
+        assert_eq!(escape("\u{1b}[1;31mFAILED\u{1b}[0m"), "FAILED");
+        assert_eq!(
+            escape("nul\0 bell\u{7}\tend\n"),
+            "nul\u{fffd} bell\u{fffd}\tend\n"
+        );
+    }
+
+    #[test]
+    fn junit_is_well_formed() {
+        let report = RunReport {
+            tests: vec![
+                outcome("A", Status::Pass, Vec::new()),
+                outcome(
+                    "B",
+                    Status::Fail,
+                    vec![Failure {
+                        message: "\u{1b}[31mExpected <1>\u{1b}[0m\0".to_string(),
+                        file: Some("tests.cpp".to_string()),
+                        line: Some(3),
+                    }],
+                ),
+            ],
+            memory: vec![MemoryFinding {
+                sanitizer: "AddressSanitizer".to_string(),
+                message: "heap-buffer-overflow\u{1}".to_string(),
+                test: None,
+            }],
+            ..RunReport::default()
+        };
+
+        let junit = report.to_junit();
+        assert!(well_formed(&junit), "{junit}");
+        assert!(junit.contains("Expected &lt;1&gt;"));
+    }
+
+    #[test]
+    fn junit_reports_crashes() {
+        let mut crashed = outcome(
+            "C",
+            Status::Crash,
+            vec![Failure {
+                message: "==1==ERROR: AddressSanitizer: SEGV on unknown address 0x000000000000\n\
+                          #0 0x55 in answer src/ex0.c:5"
+                    .to_string(),
+                file: None,
+                line: None,
+            }],
+        );
+        crashed.reason = Some("SIGSEGV".to_string());
+        let report = RunReport {
+            tests: vec![crashed, outcome("D", Status::Timeout, Vec::new())],
+            ..RunReport::default()
+        };
+
+        let junit = report.to_junit();
+        assert!(well_formed(&junit), "{junit}");
+        assert!(junit.contains(
+            "<error message=\"crashed (SIGSEGV)\">==1==ERROR: AddressSanitizer: SEGV on unknown \
+             address 0x000000000000\n#0 0x55 in answer src/ex0.c:5</error>"
+        ));
+        assert!(junit.contains("<error message=\"timed out\"/>"));
+    }
+
+    #[test]
+    fn junit_groups_interleaved_suites() {
+        let mut other = outcome("B", Status::Fail, Vec::new());
+        other.suite = "T".to_string();
+        let report = RunReport {
+            tests: vec![
+                outcome("A", Status::Pass, Vec::new()),
+                other,
+                outcome("C", Status::Crash, Vec::new()),
+            ],
+            ..RunReport::default()
+        };
+
+        let junit = report.to_junit();
+        assert!(well_formed(&junit), "{junit}");
+        assert_eq!(junit.matches("<testsuite name=\"S\"").count(), 1, "{junit}");
+        assert!(junit.contains(
+            "<testsuite name=\"S\" tests=\"2\" failures=\"0\" errors=\"1\" skipped=\"0\">"
+        ));
+        assert!(junit.contains(
+            "<testsuite name=\"T\" tests=\"1\" failures=\"1\" errors=\"0\" skipped=\"0\">"
+        ));
+    }
+
+    #[test]
+    fn spans_out_of_the_file_have_no_range() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("src/ex0.c".to_string(), "int x;\nint y = z;\n".to_string());
+        let span = |line, column, end_line, end_column| Span {
+            line,
+            column,
+            end_line,
+            end_column,
+        };
+
+        assert_eq!(
+            byte_range(&files, file_id, span(2, 9, Some(2), Some(10))),
+            Some(15..16)
+        );
+        assert_eq!(
+            byte_range(&files, file_id, span(2, 5, None, None)),
+            Some(11..17)
+        );
+        assert_eq!(byte_range(&files, file_id, span(0, 1, None, None)), None);
+        assert_eq!(byte_range(&files, file_id, span(9, 1, None, None)), None);
+
+        // A region ending on line 0 stops where it starts
+        assert_eq!(
+            byte_range(&files, file_id, span(2, 5, Some(0), None)),
+            Some(11..11)
+        );
+    }
+}
@@ -0,0 +1,316 @@
+//! The results of a run, whatever produced them.
+//!
+//! The input formats are read by adapters: [`UnitTest::outcomes`](crate::gunit::UnitTest::outcomes)
+//! for googletest, [`sarif::diagnostics`](crate::sarif::diagnostics) for the compiler and
+//! [`Asan`](crate::asan::Asan) for the sanitizers. The writers of [`render`](crate::render)
+//! only know about [`RunReport`].
+
+use crate::assertion::Assertion;
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::time::{Duration, Instant};
+
+/// Everything a run of an exercise produced
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct RunReport {
+    /// The steps of the run, in the order they ran
+    pub phases: Vec<Phase>,
+
+    /// Diagnostics of the compiler
+    pub diagnostics: Vec<Diagnostic>,
+
+    pub tests: Vec<TestOutcome>,
+
+    /// Errors found by the sanitizers
+    pub memory: Vec<MemoryFinding>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Phase {
+    pub kind: PhaseKind,
+    pub status: PhaseStatus,
+
+    #[serde(with = "seconds")]
+    pub duration: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum PhaseKind {
+    /// Starting the container
+    Setup,
+
+    /// Removing the previous build
+    Clean,
+
+    Build,
+    Test,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum PhaseStatus {
+    Success,
+    Failure,
+    Timeout,
+}
+
+/// An error of a phase, telling timeouts apart from other failures
+pub trait PhaseError {
+    fn is_timeout(&self) -> bool;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+
+    /// Path of the file on the host, or as reported when it is not part of the workspace
+    pub file: Option<String>,
+
+    pub span: Option<Span>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Severity {
+    Note,
+    Warning,
+    Error,
+}
+
+/// Lines and columns, starting from 1, the end being inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Span {
+    pub line: u32,
+    pub column: u32,
+    pub end_line: Option<u32>,
+    pub end_column: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct TestOutcome {
+    /// Suite, or class, the test belongs to
+    pub suite: String,
+    pub name: String,
+    pub status: Status,
+
+    #[serde(with = "optional_seconds")]
+    pub duration: Option<Duration>,
+
+    /// Where the test is defined, as reported by the test framework
+    pub file: Option<String>,
+    pub line: Option<u32>,
+
+    pub failures: Vec<Failure>,
+
+    /// Why the test was skipped or disabled, when known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+#[non_exhaustive]
+pub enum Status {
+    Pass,
+    Fail,
+
+    /// Skipped by the test itself while running, e.g. with `GTEST_SKIP()`
+    Skip,
+
+    /// Not run at all, e.g. a googletest test whose name starts with `DISABLED_`
+    Disabled,
+
+    Crash,
+    Timeout,
+}
+
+/// A failed assertion, or any other reason a test failed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct Failure {
+    pub message: String,
+
+    /// Where the failure happened, as reported by the test framework
+    pub file: Option<String>,
+    pub line: Option<u32>,
+}
+
+/// An error reported by a sanitizer
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[non_exhaustive]
+pub struct MemoryFinding {
+    /// e.g. `AddressSanitizer`
+    pub sanitizer: String,
+
+    /// The first line of the report, e.g. `heap-buffer-overflow on address ...`
+    pub message: String,
+
+    /// Full name of the test that was running, when each test ran in a process of its own
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub test: Option<String>,
+}
+
+impl RunReport {
+    /// Run `step`, recording how long it took and how it ended as the phase `kind`
+    pub fn phase<T, E: PhaseError>(
+        &mut self,
+        kind: PhaseKind,
+        step: impl FnOnce() -> Result<T, E>,
+    ) -> Result<T, E> {
+        let start = Instant::now();
+        let result = step();
+        let status = match &result {
+            Ok(_) => PhaseStatus::Success,
+            Err(err) if err.is_timeout() => PhaseStatus::Timeout,
+            Err(_) => PhaseStatus::Failure,
+        };
+        self.phases.push(Phase {
+            kind,
+            status,
+            duration: start.elapsed(),
+        });
+        result
+    }
+
+    /// Add the results of `other` to the ones of this report
+    pub fn merge(&mut self, other: RunReport) {
+        self.phases.extend(other.phases);
+        self.diagnostics.extend(other.diagnostics);
+        self.tests.extend(other.tests);
+        self.memory.extend(other.memory);
+    }
+
+    /// Number of tests that ended with `status`
+    pub fn count(&self, status: Status) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| test.status == status)
+            .count()
+    }
+
+    pub fn passed(&self) -> usize {
+        self.count(Status::Pass)
+    }
+
+    /// Number of tests that failed, crashed or timed out
+    pub fn failed(&self) -> usize {
+        self.tests
+            .iter()
+            .filter(|test| test.status.is_failure())
+            .count()
+    }
+
+    /// Whether a test failed or a sanitizer found an error
+    pub fn has_failed(&self) -> bool {
+        self.failed() > 0 || !self.memory.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+
+    /// The `count` tests that took the longest, the slowest first, leaving out the tests whose
+    /// duration is unknown
+    pub fn slowest(&self, count: usize) -> Vec<&TestOutcome> {
+        let mut tests = self
+            .tests
+            .iter()
+            .filter(|test| test.duration.is_some())
+            .collect::<Vec<_>>();
+        tests.sort_by_key(|test| Reverse(test.duration));
+        tests.truncate(count);
+        tests
+    }
+
+    /// The tests that took longer than `threshold`, the slowest first
+    pub fn slower_than(&self, threshold: Duration) -> Vec<&TestOutcome> {
+        let mut tests = self.slowest(self.tests.len());
+        tests.retain(|test| test.duration.is_some_and(|duration| duration > threshold));
+        tests
+    }
+
+    /// How long the tests took: the test phase if it was timed, the tests themselves otherwise
+    pub fn duration(&self) -> Duration {
+        self.phases
+            .iter()
+            .find(|phase| phase.kind == PhaseKind::Test)
+            .map(|phase| phase.duration)
+            .unwrap_or_else(|| self.tests.iter().filter_map(|test| test.duration).sum())
+    }
+}
+
+impl TestOutcome {
+    /// Full name of the test, e.g. `Suite.name`, or only its name outside of a suite
+    pub fn full_name(&self) -> String {
+        if self.suite.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{}.{}", self.suite, self.name)
+        }
+    }
+}
+
+impl Status {
+    /// Whether the test failed, crashed or timed out
+    pub fn is_failure(self) -> bool {
+        matches!(self, Status::Fail | Status::Crash | Status::Timeout)
+    }
+}
+
+impl Failure {
+    /// The structured assertion behind this failure, if googletest's message could be parsed
+    pub fn assertion(&self) -> Option<Assertion> {
+        Assertion::parse(&self.message)
+    }
+}
+
+/// Durations as a number of seconds
+mod seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_f64(duration.as_secs_f64())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        Duration::try_from_secs_f64(f64::deserialize(deserializer)?)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+pub(crate) mod optional_seconds {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(
+        duration: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match duration {
+            Some(duration) => serializer.serialize_some(&duration.as_secs_f64()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Option::<f64>::deserialize(deserializer)?
+            .map(Duration::try_from_secs_f64)
+            .transpose()
+            .map_err(serde::de::Error::custom)
+    }
+}
@@ -0,0 +1,225 @@
+use clap::ValueEnum;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+macro_rules! template_files {
+    ($language:literal: $($source:literal => $destination:literal),* $(,)?) => {
+        &[$((
+            $destination,
+            include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/templates/", $language, "/", $source)),
+        )),*]
+    };
+}
+
+const C_TEMPLATE: &[(&str, &str)] = template_files!("c":
+    "dojo_assignment.json" => "dojo_assignment.json",
+    "docker-compose.yml" => "docker-compose.yml",
+    "Dockerfile" => "Dockerfile",
+    "gitignore" => ".gitignore",
+    "src/Makefile" => "src/Makefile",
+    "src/module.h" => "src/{{module}}.h",
+    "src/module.c" => "src/{{module}}.c",
+    "tests/tests.cpp" => "tests/tests.cpp",
+);
+
+const CPP_TEMPLATE: &[(&str, &str)] = template_files!("cpp":
+    "dojo_assignment.json" => "dojo_assignment.json",
+    "docker-compose.yml" => "docker-compose.yml",
+    "Dockerfile" => "Dockerfile",
+    "gitignore" => ".gitignore",
+    "src/Makefile" => "src/Makefile",
+    "src/module.hpp" => "src/{{module}}.hpp",
+    "src/module.cpp" => "src/{{module}}.cpp",
+    "tests/tests.cpp" => "tests/tests.cpp",
+);
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("I/O error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("unknown template variable '{0}'")]
+    UnknownVariable(String),
+}
+
+/// Languages of the built-in exercise templates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Language {
+    C,
+    Cpp,
+}
+
+/// Files of a new exercise, whose paths and contents may refer to variables as `{{name}}`
+#[derive(Debug, Clone)]
+pub struct Template {
+    files: Vec<(String, String)>,
+}
+
+impl Template {
+    pub fn builtin(language: Language) -> Self {
+        let files = match language {
+            Language::C => C_TEMPLATE,
+            Language::Cpp => CPP_TEMPLATE,
+        };
+
+        Self {
+            files: files
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Load a local template from every file of `dir`
+    pub fn from_dir(dir: &Path) -> Result<Self, TemplateError> {
+        let mut files = Vec::new();
+        let mut pending = vec![dir.to_path_buf()];
+        while let Some(current) = pending.pop() {
+            for entry in std::fs::read_dir(&current)? {
+                let path = entry?.path();
+                if path.file_name().is_some_and(|name| name == ".git") {
+                    continue;
+                }
+
+                if path.is_dir() {
+                    pending.push(path);
+                } else {
+                    let relative = path.strip_prefix(dir).unwrap_or(&path);
+                    files.push((
+                        relative.display().to_string(),
+                        std::fs::read_to_string(&path)?,
+                    ));
+                }
+            }
+        }
+
+        files.sort();
+        Ok(Self { files })
+    }
+
+    /// Render every file of the template into `destination`, returning the created files
+    pub fn render(
+        &self,
+        destination: &Path,
+        variables: &BTreeMap<String, String>,
+    ) -> Result<Vec<PathBuf>, TemplateError> {
+        // Render everything first so that a typo does not leave a half-created exercise
+        let files = self
+            .files
+            .iter()
+            .map(|(path, content)| {
+                Ok((
+                    destination.join(render(path, variables)?),
+                    render(content, variables)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, TemplateError>>()?;
+
+        for (path, content) in &files {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            std::fs::write(path, content)?;
+        }
+
+        Ok(files.into_iter().map(|(path, _)| path).collect())
+    }
+}
+
+/// Replace every `{{variable}}` of `template`, the name of the variable being an identifier.
+/// Any other `{{`, e.g. of nested C initializers, is kept, and `\{{` is always kept as `{{`.
+pub fn render(
+    template: &str,
+    variables: &BTreeMap<String, String>,
+) -> Result<String, TemplateError> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            rendered.push_str(&rest[..start - 1]);
+            rendered.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+
+        rendered.push_str(&rest[..start]);
+        let variable = rest[start + 2..]
+            .split_once("}}")
+            .map(|(name, after)| (name.trim(), after))
+            .filter(|(name, _)| is_identifier(name));
+        match variable {
+            Some((name, after)) => {
+                let value = variables
+                    .get(name)
+                    .ok_or_else(|| TemplateError::UnknownVariable(name.to_string()))?;
+                rendered.push_str(value);
+                rest = after;
+            }
+            None => {
+                rendered.push_str("{{");
+                rest = &rest[start + 2..];
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables() -> BTreeMap<String, String> {
+        BTreeMap::from([("module".to_string(), "ex0".to_string())])
+    }
+
+    #[test]
+    fn render_replaces_variables() {
+        assert_eq!(
+            render("#include \"{{module}}.h\" // {{ module }}", &variables()).unwrap(),
+            "#include \"ex0.h\" // ex0"
+        );
+        assert!(matches!(
+            render("{{service}}", &variables()),
+            Err(TemplateError::UnknownVariable(name)) if name == "service"
+        ));
+    }
+
+    #[test]
+    fn render_keeps_other_braces() {
+        let nested = "int a[2][2] = {{1, 2}, {3, 4}};\nint b[1][1] = {{0}};";
+        assert_eq!(render(nested, &variables()).unwrap(), nested);
+        assert_eq!(
+            render("struct s s = {{", &variables()).unwrap(),
+            "struct s s = {{"
+        );
+    }
+
+    #[test]
+    fn render_unescapes_braces() {
+        assert_eq!(
+            render("int b[1][1] = \\{{x}};", &variables()).unwrap(),
+            "int b[1][1] = {{x}};"
+        );
+    }
+
+    #[test]
+    fn builtin_templates_render() {
+        let variables = ["module", "guard", "suite", "service", "volume"]
+            .map(|name| (name.to_string(), "x".to_string()))
+            .into();
+        for language in [Language::C, Language::Cpp] {
+            for (path, content) in &Template::builtin(language).files {
+                render(path, &variables).unwrap();
+                render(content, &variables).unwrap();
+            }
+        }
+    }
+}
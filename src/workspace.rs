@@ -0,0 +1,124 @@
+use crate::dojo::ASSIGNMENT_FILE;
+use itertools::Itertools;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+/// Environment variable pointing to the workspace, used when no path is given
+pub const WORKSPACE_ENV: &str = "NEODOJO_WORKSPACE";
+
+/// Directory where neodojo keeps its state within the workspace
+pub const STATE_DIR: &str = ".neodojo";
+
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum WorkspaceError {
+    #[error(
+        "no {ASSIGNMENT_FILE} found, searched:\n{}",
+        searched.iter().map(|dir| format!("    {}", dir.display())).join("\n")
+    )]
+    NotFound { searched: Vec<PathBuf> },
+}
+
+/// Find the root of the workspace like git or cargo do, by walking up from `start` (or
+/// `$NEODOJO_WORKSPACE`, or the current directory) until a `dojo_assignment.json` is found.
+///
+/// The root is returned relative to the current directory when `start` is relative, so that
+/// paths printed afterwards stay short.
+pub fn discover(start: Option<&Path>) -> Result<PathBuf, WorkspaceError> {
+    let start = start
+        .map(Path::to_path_buf)
+        .or_else(|| std::env::var_os(WORKSPACE_ENV).map(PathBuf::from))
+        .unwrap_or_else(|| PathBuf::from("."));
+
+    let absolute = start.canonicalize().map_err(|_| WorkspaceError::NotFound {
+        searched: vec![start.clone()],
+    })?;
+
+    let mut searched = Vec::new();
+    let mut relative = start.clone();
+    for dir in absolute.ancestors() {
+        if dir.join(ASSIGNMENT_FILE).is_file() {
+            return Ok(if start.is_absolute() {
+                dir.to_path_buf()
+            } else {
+                clean(&relative)
+            });
+        }
+
+        searched.push(dir.to_path_buf());
+        relative.push("..");
+    }
+
+    Err(WorkspaceError::NotFound { searched })
+}
+
+/// The state directory of the workspace, created if needed along with a `.gitignore` so that
+/// it never ends up in the submission
+pub fn state_dir(root: &Path) -> std::io::Result<PathBuf> {
+    let dir = root.join(STATE_DIR);
+    std::fs::create_dir_all(&dir)?;
+    let gitignore = dir.join(".gitignore");
+    if !gitignore.exists() {
+        std::fs::write(gitignore, "*\n")?;
+    }
+    Ok(dir)
+}
+
+/// Remove the `.` components of `path`, and the `..` ones along with the directory they leave
+pub(crate) fn clean(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(cleaned.components().next_back(), Some(Component::Normal(_))) =>
+            {
+                cleaned.pop();
+            }
+            component => cleaned.push(component),
+        }
+    }
+
+    if cleaned.as_os_str().is_empty() {
+        PathBuf::from(".")
+    } else {
+        cleaned
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clean_collapses_parent_directories() {
+        assert_eq!(clean(Path::new("src/..")), PathBuf::from("."));
+        assert_eq!(clean(Path::new("./src/tests/../..")), PathBuf::from("."));
+        assert_eq!(clean(Path::new("src/tests/..")), PathBuf::from("src"));
+        assert_eq!(clean(Path::new("../src/..")), PathBuf::from(".."));
+        assert_eq!(clean(Path::new("../..")), PathBuf::from("../.."));
+        assert_eq!(clean(Path::new("/ws/src/..")), PathBuf::from("/ws"));
+    }
+
+    #[test]
+    fn discover_walks_up_to_the_assignment() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir_all(root.join("src/nested")).unwrap();
+        std::fs::write(root.join(ASSIGNMENT_FILE), "{}").unwrap();
+
+        assert_eq!(discover(Some(&root.join("src/nested"))).unwrap(), root);
+        assert_eq!(discover(Some(&root)).unwrap(), root);
+    }
+
+    #[test]
+    fn discover_lists_the_directories_searched() {
+        let dir = tempfile::tempdir().unwrap();
+        let start = dir.path().canonicalize().unwrap();
+
+        match discover(Some(&start)) {
+            Err(WorkspaceError::NotFound { searched }) => assert_eq!(searched[0], start),
+            Ok(root) => panic!("found {}", root.display()),
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#include "{{module}}.h"
+
+int answer(void) {
+    return 0;
+}
@@ -0,0 +1,6 @@
+#ifndef {{guard}}
+#define {{guard}}
+
+int answer(void);
+
+#endif
@@ -0,0 +1,9 @@
+#include <gtest/gtest.h>
+
+extern "C" {
+#include "{{module}}.h"
+}
+
+TEST({{suite}}, Answer) {
+    EXPECT_EQ(answer(), 42);
+}
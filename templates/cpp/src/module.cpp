@@ -0,0 +1,5 @@
+#include "{{module}}.hpp"
+
+int answer() {
+    return 0;
+}
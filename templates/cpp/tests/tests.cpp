@@ -0,0 +1,7 @@
+#include <gtest/gtest.h>
+
+#include "{{module}}.hpp"
+
+TEST({{suite}}, Answer) {
+    EXPECT_EQ(answer(), 42);
+}